@@ -1,6 +1,2 @@
-pub const TRACK_LENGTH: f32 = 200.0;
-
 pub const HURDLE_HEIGHT: f32 = 2.0;
 pub const HURDLE_WIDTH: f32 = 0.3;
-pub const HURDLE_SPACING: f32 = 15.0;
-pub const BEFORE_FIRST: f32 = 15.0;
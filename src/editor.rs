@@ -0,0 +1,648 @@
+use bevy::prelude::*;
+use bevy::render::camera::{Camera, CameraPlugin};
+use bevy_egui_kbgp::bevy_egui::EguiContext;
+use bevy_egui_kbgp::egui;
+use bevy_egui_kbgp::prelude::*;
+
+use crate::game_systems::ground::{spawn_distance_markers, spawn_ground_section, surface_patches};
+use crate::game_systems::hurdles::{hurdle_definitions, spawn_hurdle};
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::pickups::{pickup_definitions, spawn_pickup};
+use crate::game_systems::wind::{spawn_wind_zone, wind_zones};
+use crate::global_types::{
+    AppState, CameraFollowTarget, DespawnWithLevel, Hurdle, MenuState, TrackSeed,
+};
+use crate::loading::{TextureAssets, TrackAssets};
+use crate::track::{
+    CompetitorDefinition, HurdleDefinition, HurdleLayout, ObstacleMotion, OverheadBarDefinition,
+    SelectedTrack, Terrain, TrackDefinition, WaterPitDefinition,
+};
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrackEditor>();
+        app.add_system_set(SystemSet::on_enter(AppState::Editor).with_system(enter_editor));
+        app.add_system_set(SystemSet::on_exit(AppState::Editor).with_system(exit_editor));
+        app.add_system_set({
+            SystemSet::on_update(AppState::Editor)
+                .with_system(editor_panel)
+                .with_system(edit_with_mouse)
+                .with_system(rebuild_preview)
+                .with_system(highlight_selected_hurdle)
+        });
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Menu(MenuState::Main)).with_system(
+                |mut track_editor: ResMut<TrackEditor>| {
+                    track_editor.test_run = false;
+                },
+            ),
+        );
+    }
+}
+
+/// The track being edited, and the state of the editor around it.
+pub struct TrackEditor {
+    track: TrackDefinition,
+    opened: bool,
+    selected: Option<usize>,
+    dragging: Option<Drag>,
+    view_x: f32,
+    needs_rebuild: bool,
+    file_path: String,
+    status: String,
+    /// Whether the current race is a test run started from the editor.
+    pub test_run: bool,
+}
+
+impl Default for TrackEditor {
+    fn default() -> Self {
+        Self {
+            track: TrackDefinition::default(),
+            opened: false,
+            selected: None,
+            dragging: None,
+            view_x: 0.0,
+            needs_rebuild: false,
+            file_path: "assets/tracks/custom.track.ron".to_owned(),
+            status: String::new(),
+            test_run: false,
+        }
+    }
+}
+
+enum Drag {
+    Hurdle {
+        index: usize,
+        grab_offset: f32,
+    },
+    View {
+        start_cursor_x: f32,
+        start_view_x: f32,
+    },
+}
+
+impl TrackEditor {
+    /// Start editing `track`. The editor only deals with explicit hurdles, so generated hurdles are
+    /// fixed in place using `track_seed`, and endless tracks become regular ones.
+    fn open(&mut self, mut track: TrackDefinition, track_seed: &TrackSeed) {
+        if !matches!(track.hurdles, HurdleLayout::Explicit(_)) {
+            track.hurdles = HurdleLayout::Explicit(hurdle_definitions(&track, track_seed));
+        }
+        track.endless = None;
+        self.track = track;
+        self.opened = true;
+        self.selected = None;
+        self.dragging = None;
+        self.needs_rebuild = true;
+    }
+
+    fn hurdles(&self) -> &[HurdleDefinition] {
+        match &self.track.hurdles {
+            HurdleLayout::Explicit(hurdles) => hurdles,
+            HurdleLayout::Generated { .. } | HurdleLayout::Profiled(_) => &[],
+        }
+    }
+
+    fn hurdles_mut(&mut self) -> &mut Vec<HurdleDefinition> {
+        if !matches!(self.track.hurdles, HurdleLayout::Explicit(_)) {
+            self.track.hurdles = HurdleLayout::Explicit(Vec::new());
+        }
+        match &mut self.track.hurdles {
+            HurdleLayout::Explicit(hurdles) => hurdles,
+            HurdleLayout::Generated { .. } | HurdleLayout::Profiled(_) => unreachable!(),
+        }
+    }
+
+    /// The hurdle under the world position, if any.
+    fn hurdle_at(&self, terrain: &Terrain, position: Vec2) -> Option<usize> {
+        self.hurdles().iter().position(|hurdle| {
+            let ground = terrain.height_at(hurdle.position);
+            (position.x - hurdle.position).abs() < 0.5 * hurdle.dimensions.width + 0.3
+                && ground - 0.3 < position.y
+                && position.y < ground + hurdle.dimensions.height + 0.3
+        })
+    }
+
+    fn delete_selected(&mut self) {
+        if let Some(index) = self.selected.take() {
+            self.hurdles_mut().remove(index);
+            self.dragging = None;
+            self.needs_rebuild = true;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&mut self) {
+        let result = ron::ser::to_string_pretty(&self.track, Default::default())
+            .map_err(anyhow::Error::from)
+            .and_then(|serialized| Ok(std::fs::write(&self.file_path, serialized)?));
+        self.status = match result {
+            Ok(()) => format!("Saved to {}", self.file_path),
+            Err(err) => format!("Saving failed: {}", err),
+        };
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load(&mut self, track_seed: &TrackSeed) {
+        let result = std::fs::read(&self.file_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(ron::de::from_bytes::<TrackDefinition>(&bytes)?));
+        self.status = match result {
+            Ok(track) => {
+                self.open(track, track_seed);
+                format!("Loaded {}", self.file_path)
+            }
+            Err(err) => format!("Loading failed: {}", err),
+        };
+    }
+}
+
+/// The camera follows this entity while in the editor.
+#[derive(Component)]
+struct EditorView;
+
+/// Marks the preview entities of the hurdle with this index.
+#[derive(Component)]
+struct EditorHurdle(usize);
+
+fn enter_editor(
+    mut commands: Commands,
+    mut track_editor: ResMut<TrackEditor>,
+    track_assets: Res<TrackAssets>,
+    tracks: Res<Assets<TrackDefinition>>,
+    track_seed: Res<TrackSeed>,
+) {
+    if !track_editor.opened {
+        if let Some(track) = tracks.get(&track_assets.standard) {
+            track_editor.open(track.clone(), &track_seed);
+        }
+    }
+    track_editor.test_run = false;
+    track_editor.needs_rebuild = true;
+    commands
+        .spawn()
+        .insert(Transform::from_xyz(track_editor.view_x, 0.0, 0.0))
+        .insert(GlobalTransform::default())
+        .insert(CameraFollowTarget)
+        .insert(EditorView);
+}
+
+fn exit_editor(mut commands: Commands, query: Query<Entity, With<EditorView>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn editor_panel(
+    mut egui_context: ResMut<EguiContext>,
+    mut track_editor: ResMut<TrackEditor>,
+    mut state: ResMut<State<AppState>>,
+    mut selected_track: ResMut<SelectedTrack>,
+    mut tracks: ResMut<Assets<TrackDefinition>>,
+    track_assets: Res<TrackAssets>,
+    track_seed: Res<TrackSeed>,
+    mut view_query: Query<&mut Transform, With<EditorView>>,
+) {
+    let track_editor = &mut *track_editor;
+    let mut changed = false;
+    egui::SidePanel::left("track-editor")
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.heading("Track Editor");
+                if ui
+                    .button("Test Run")
+                    .kbgp_navigation()
+                    .kbgp_initial_focus()
+                    .clicked()
+                {
+                    selected_track.0 = tracks.add(track_editor.track.clone());
+                    state.set(AppState::ClearLevelAndThenLoad).unwrap();
+                    ui.kbgp_clear_input();
+                    track_editor.test_run = true;
+                }
+                if ui.button("Main Menu").kbgp_navigation().clicked() {
+                    state.set(AppState::Menu(MenuState::Main)).unwrap();
+                    ui.kbgp_clear_input();
+                }
+
+                ui.separator();
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    ui.add(egui::TextEdit::singleline(&mut track_editor.file_path))
+                        .kbgp_navigation();
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").kbgp_navigation().clicked() {
+                            track_editor.save();
+                        }
+                        if ui.button("Load").kbgp_navigation().clicked() {
+                            track_editor.load(&track_seed);
+                        }
+                    });
+                }
+                if ui.button("Reset to Standard").kbgp_navigation().clicked() {
+                    if let Some(track) = tracks.get(&track_assets.standard) {
+                        track_editor.open(track.clone(), &track_seed);
+                        track_editor.status = "Loaded the standard track".to_owned();
+                    }
+                }
+                if !track_editor.status.is_empty() {
+                    ui.label(&track_editor.status);
+                }
+
+                ui.separator();
+                let track = &mut track_editor.track;
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.add(egui::TextEdit::singleline(&mut track.name))
+                        .kbgp_navigation();
+                });
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut track.bounds.right)
+                            .speed(1.0)
+                            .clamp_range(20.0..=1000.0)
+                            .prefix("Length: ")
+                            .suffix("m"),
+                    )
+                    .kbgp_navigation()
+                    .changed();
+
+                let mut view_x = track_editor.view_x;
+                if ui
+                    .add(
+                        egui::Slider::new(&mut view_x, track.bounds.left..=track.bounds.right)
+                            .text("View"),
+                    )
+                    .kbgp_navigation()
+                    .changed()
+                {
+                    track_editor.view_x = view_x;
+                    for mut transform in view_query.iter_mut() {
+                        transform.translation.x = view_x;
+                    }
+                }
+
+                ui.separator();
+                ui.label("Drag hurdles to move them, or the background to scroll.");
+                if ui.button("Add Hurdle").kbgp_navigation().clicked() {
+                    let position = track_editor.view_x.max(1.0);
+                    let hurdles = track_editor.hurdles_mut();
+                    hurdles.push(HurdleDefinition {
+                        position,
+                        dimensions: Default::default(),
+                        motion: None,
+                        water_pit: None,
+                        overhead_bar: None,
+                    });
+                    track_editor.selected = Some(hurdles.len() - 1);
+                    changed = true;
+                }
+                if let Some(index) = track_editor.selected {
+                    changed |= hurdle_ui(ui, &mut track_editor.hurdles_mut()[index]);
+                    if ui.button("Delete Hurdle").kbgp_navigation().clicked() {
+                        track_editor.delete_selected();
+                    }
+                }
+
+                ui.separator();
+                ui.label("Competitors:");
+                let competitors = &mut track_editor.track.competitors;
+                let mut to_remove = None;
+                for (index, competitor) in competitors.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut competitor.start)
+                                .speed(0.5)
+                                .prefix("Start: "),
+                        )
+                        .kbgp_navigation();
+                        ui.add(
+                            egui::DragValue::new(&mut competitor.speed)
+                                .speed(0.1)
+                                .clamp_range(0.5..=10.0)
+                                .prefix("Speed: "),
+                        )
+                        .kbgp_navigation();
+                        if ui.button("Remove").kbgp_navigation().clicked() {
+                            to_remove = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = to_remove {
+                    competitors.remove(index);
+                }
+                if ui.button("Add Competitor").kbgp_navigation().clicked() {
+                    let start = competitors.last().map_or(2.0, |last| last.start + 3.0);
+                    competitors.push(CompetitorDefinition { start, speed: 4.0 });
+                }
+            });
+        });
+    if changed {
+        track_editor.needs_rebuild = true;
+    }
+}
+
+/// Edit the dimensions, motion and water pit of a hurdle. Returns whether anything changed.
+fn hurdle_ui(ui: &mut egui::Ui, hurdle: &mut HurdleDefinition) -> bool {
+    let mut changed = false;
+    changed |= ui
+        .add(
+            egui::DragValue::new(&mut hurdle.position)
+                .speed(0.1)
+                .prefix("Position: ")
+                .suffix("m"),
+        )
+        .kbgp_navigation()
+        .changed();
+    ui.horizontal(|ui| {
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut hurdle.dimensions.width)
+                    .speed(0.05)
+                    .clamp_range(0.1..=3.0)
+                    .prefix("Width: "),
+            )
+            .kbgp_navigation()
+            .changed();
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut hurdle.dimensions.height)
+                    .speed(0.05)
+                    .clamp_range(0.2..=5.0)
+                    .prefix("Height: "),
+            )
+            .kbgp_navigation()
+            .changed();
+    });
+
+    let motion_name = |motion: &Option<ObstacleMotion>| match motion {
+        None => "Static",
+        Some(ObstacleMotion::Slide { .. }) => "Slide",
+        Some(ObstacleMotion::Swing { .. }) => "Swing",
+        Some(ObstacleMotion::RiseAndFall { .. }) => "Rise and Fall",
+    };
+    egui::ComboBox::from_label("Motion")
+        .selected_text(motion_name(&hurdle.motion))
+        .show_ui(ui, |ui| {
+            for option in [
+                None,
+                Some(ObstacleMotion::Slide {
+                    amplitude: 1.0,
+                    period: 3.0,
+                    phase: 0.0,
+                }),
+                Some(ObstacleMotion::Swing {
+                    pivot_height: 4.0,
+                    amplitude: 0.8,
+                    period: 3.0,
+                    phase: 0.0,
+                }),
+                Some(ObstacleMotion::RiseAndFall {
+                    height: 1.5,
+                    down_time: 2.0,
+                    up_time: 1.0,
+                    transition_time: 0.5,
+                    phase: 0.0,
+                }),
+            ] {
+                let selected = motion_name(&hurdle.motion) == motion_name(&option);
+                if ui
+                    .selectable_label(selected, motion_name(&option))
+                    .clicked()
+                    && !selected
+                {
+                    hurdle.motion = option;
+                    changed = true;
+                }
+            }
+        });
+    match &mut hurdle.motion {
+        None => {}
+        Some(ObstacleMotion::Slide {
+            amplitude,
+            period,
+            phase,
+        }) => {
+            changed |= parameter(ui, "Amplitude: ", amplitude);
+            changed |= parameter(ui, "Period: ", period);
+            changed |= parameter(ui, "Phase: ", phase);
+        }
+        Some(ObstacleMotion::Swing {
+            pivot_height,
+            amplitude,
+            period,
+            phase,
+        }) => {
+            changed |= parameter(ui, "Pivot height: ", pivot_height);
+            changed |= parameter(ui, "Amplitude: ", amplitude);
+            changed |= parameter(ui, "Period: ", period);
+            changed |= parameter(ui, "Phase: ", phase);
+        }
+        Some(ObstacleMotion::RiseAndFall {
+            height,
+            down_time,
+            up_time,
+            transition_time,
+            phase,
+        }) => {
+            changed |= parameter(ui, "Height: ", height);
+            changed |= parameter(ui, "Down time: ", down_time);
+            changed |= parameter(ui, "Up time: ", up_time);
+            changed |= parameter(ui, "Transition time: ", transition_time);
+            changed |= parameter(ui, "Phase: ", phase);
+        }
+    }
+
+    let mut has_water_pit = hurdle.water_pit.is_some();
+    if ui
+        .checkbox(&mut has_water_pit, "Water pit")
+        .kbgp_navigation()
+        .changed()
+    {
+        hurdle.water_pit = has_water_pit.then(WaterPitDefinition::default);
+        changed = true;
+    }
+    if let Some(water_pit) = &mut hurdle.water_pit {
+        changed |= parameter(ui, "Pit length: ", &mut water_pit.length);
+        changed |= parameter(ui, "Penalty: ", &mut water_pit.penalty);
+    }
+
+    let mut has_overhead_bar = hurdle.overhead_bar.is_some();
+    if ui
+        .checkbox(&mut has_overhead_bar, "Overhead bar")
+        .kbgp_navigation()
+        .changed()
+    {
+        hurdle.overhead_bar = has_overhead_bar.then(OverheadBarDefinition::default);
+        changed = true;
+    }
+    if let Some(overhead_bar) = &mut hurdle.overhead_bar {
+        changed |= parameter(ui, "Bar offset: ", &mut overhead_bar.offset);
+        changed |= parameter(ui, "Bar length: ", &mut overhead_bar.length);
+        changed |= parameter(ui, "Clearance: ", &mut overhead_bar.clearance);
+    }
+    changed
+}
+
+fn parameter(ui: &mut egui::Ui, label: &str, value: &mut f32) -> bool {
+    ui.add(egui::DragValue::new(value).speed(0.05).prefix(label))
+        .kbgp_navigation()
+        .changed()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn edit_with_mouse(
+    mut egui_context: ResMut<EguiContext>,
+    mut track_editor: ResMut<TrackEditor>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    cameras_query: Query<(&Camera, &GlobalTransform, &OrthographicProjection)>,
+    mut view_query: Query<&mut Transform, With<EditorView>>,
+    terrain: Res<Terrain>,
+) {
+    let ctx = egui_context.ctx_mut();
+    if keys.just_pressed(KeyCode::Delete) && !ctx.wants_keyboard_input() {
+        track_editor.delete_selected();
+    }
+    if mouse_buttons.just_released(MouseButton::Left) {
+        track_editor.dragging = None;
+    }
+
+    let (camera, camera_transform, projection) = if let Some(camera) = cameras_query
+        .iter()
+        .find(|(camera, _, _)| camera.name.as_deref() == Some(CameraPlugin::CAMERA_2D))
+    {
+        camera
+    } else {
+        return;
+    };
+    let cursor = if let Some(cursor) = windows.get(camera.window).and_then(|window| {
+        Some(window.cursor_position()? - Vec2::new(window.width(), window.height()) * 0.5)
+    }) {
+        cursor
+    } else {
+        return;
+    };
+    let world_position = camera_transform.translation.truncate() + cursor * projection.scale;
+
+    if mouse_buttons.just_pressed(MouseButton::Left) && !ctx.wants_pointer_input() {
+        track_editor.selected = track_editor.hurdle_at(&terrain, world_position);
+        track_editor.dragging = Some(if let Some(index) = track_editor.selected {
+            Drag::Hurdle {
+                index,
+                grab_offset: world_position.x - track_editor.hurdles()[index].position,
+            }
+        } else {
+            Drag::View {
+                start_cursor_x: cursor.x,
+                start_view_x: track_editor.view_x,
+            }
+        });
+    }
+
+    match track_editor.dragging {
+        None => {}
+        Some(Drag::Hurdle { index, grab_offset }) => {
+            let bounds = track_editor.track.bounds;
+            let position = ((world_position.x - grab_offset) * 10.0).round() / 10.0;
+            let position = position.clamp(bounds.left, bounds.right);
+            let hurdle = &mut track_editor.hurdles_mut()[index];
+            if hurdle.position != position {
+                hurdle.position = position;
+                track_editor.needs_rebuild = true;
+            }
+        }
+        Some(Drag::View {
+            start_cursor_x,
+            start_view_x,
+        }) => {
+            let bounds = track_editor.track.bounds;
+            let view_x = (start_view_x - (cursor.x - start_cursor_x) * projection.scale)
+                .clamp(bounds.left, bounds.right);
+            track_editor.view_x = view_x;
+            for mut transform in view_query.iter_mut() {
+                transform.translation.x = view_x;
+            }
+        }
+    }
+}
+
+/// Rebuild the level from the edited track, so that it looks like it would in the race.
+#[allow(clippy::too_many_arguments)]
+fn rebuild_preview(
+    mut commands: Commands,
+    mut track_editor: ResMut<TrackEditor>,
+    level_query: Query<Entity, With<DespawnWithLevel>>,
+    mut terrain: ResMut<Terrain>,
+    track_seed: Res<TrackSeed>,
+    texture_assets: Res<TextureAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !track_editor.needs_rebuild {
+        return;
+    }
+    track_editor.needs_rebuild = false;
+    for entity in level_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let track = &track_editor.track;
+    *terrain = Terrain::new(track);
+    let lane_layout = LaneLayout::default();
+    let bounds = track.bounds.left..track.bounds.right;
+    spawn_ground_section(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &terrain,
+        &lane_layout,
+        &surface_patches(track, &track_seed),
+        bounds.clone(),
+    );
+    spawn_distance_markers(
+        &mut commands,
+        &terrain,
+        &lane_layout,
+        (bounds.start + 1.0)..bounds.end,
+    );
+    for (index, hurdle) in track_editor.hurdles().iter().enumerate() {
+        for entity in spawn_hurdle(
+            &mut commands,
+            &texture_assets,
+            &terrain,
+            &lane_layout,
+            track.hurdle_rules,
+            hurdle,
+        ) {
+            commands.entity(entity).insert(EditorHurdle(index));
+        }
+    }
+    for zone in wind_zones(track, &track_seed).iter() {
+        spawn_wind_zone(&mut commands, &terrain, &lane_layout, zone);
+    }
+    for pickup in pickup_definitions(track, &track_seed).iter() {
+        spawn_pickup(&mut commands, &terrain, &lane_layout, pickup);
+    }
+}
+
+fn highlight_selected_hurdle(
+    track_editor: Res<TrackEditor>,
+    mut query: Query<(&EditorHurdle, &mut Sprite), With<Hurdle>>,
+) {
+    for (&EditorHurdle(index), mut sprite) in query.iter_mut() {
+        let color = if Some(index) == track_editor.selected {
+            Color::rgb(1.0, 0.5, 0.5)
+        } else {
+            Color::WHITE
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
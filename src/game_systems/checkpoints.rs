@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::game_systems::hurdle_profile::{profiled_layout, ProfiledLayout, SectionKind};
+use crate::game_systems::hurdles::hurdle_definitions;
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::pogo::RespawnPlayer;
+use crate::global_types::{
+    AppState, DespawnWithLevel, GameOver, MenuState, PlayerStatus, TrackSeed,
+};
+use crate::track::{CurrentTrack, HurdleLayout, Terrain};
+
+pub struct CheckpointsPlugin;
+
+impl Plugin for CheckpointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerFault>();
+        app.init_resource::<CheckpointMode>();
+        app.init_resource::<Checkpoints>();
+        app.add_system_set(SystemSet::on_enter(AppState::LoadLevel).with_system(add_checkpoints));
+        app.add_system_set({
+            SystemSet::on_update(AppState::Game)
+                .with_system(update_reached_checkpoint)
+                .with_system(handle_player_faults)
+        });
+    }
+}
+
+/// Something the player did that ends the race - unless playing with checkpoints, in which case
+/// the player respawns at the last checkpoint instead.
+pub struct PlayerFault(pub GameOver);
+
+/// Whether injuries and fouls respawn the player instead of ending the race.
+#[derive(Default)]
+pub struct CheckpointMode(pub bool);
+
+/// Faults right after a respawn are leftovers from before it, so they are ignored.
+const RESPAWN_GRACE: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+struct Checkpoints {
+    positions: Vec<f32>,
+    reached: Option<usize>,
+    last_respawn: Option<Duration>,
+}
+
+impl Checkpoints {
+    fn respawn_position(&self) -> f32 {
+        self.reached.map_or(0.0, |index| self.positions[index])
+    }
+}
+
+#[derive(Component)]
+struct CheckpointMarker(usize);
+
+const CHECKPOINT_COLOR: Color = Color::rgb(0.9, 0.8, 0.2);
+const REACHED_CHECKPOINT_COLOR: Color = Color::rgb(0.2, 0.8, 0.3);
+
+#[allow(clippy::too_many_arguments)]
+fn add_checkpoints(
+    mut commands: Commands,
+    checkpoint_mode: Res<CheckpointMode>,
+    mut checkpoints: ResMut<Checkpoints>,
+    current_track: Res<CurrentTrack>,
+    track_seed: Res<TrackSeed>,
+    terrain: Res<Terrain>,
+    lane_layout: Res<LaneLayout>,
+) {
+    *checkpoints = Checkpoints::default();
+    let every_hurdles = current_track.0.checkpoints.every_hurdles;
+    if !checkpoint_mode.0 || every_hurdles == 0 || current_track.0.endless.is_some() {
+        return;
+    }
+
+    let track = &current_track.0;
+    checkpoints.positions = if let HurdleLayout::Profiled(profile) = &track.hurdles {
+        profiled_checkpoints(&profiled_layout(profile, track, &track_seed), every_hurdles)
+    } else {
+        // Place each checkpoint halfway between the hurdle (and what's attached to it) and the
+        // next hurdle
+        let mut hurdles = hurdle_definitions(track, &track_seed);
+        hurdles.sort_by(|a, b| a.position.partial_cmp(&b.position).unwrap());
+        hurdles
+            .iter()
+            .zip(hurdles.iter().skip(1))
+            .skip(every_hurdles - 1)
+            .step_by(every_hurdles)
+            .map(|(hurdle, next_hurdle)| {
+                let cleared_at = hurdle.cleared_at();
+                let next_at = next_hurdle.position - 0.5 * next_hurdle.dimensions.width;
+                0.5 * (cleared_at + next_at)
+            })
+            .collect()
+    };
+
+    let lane = lane_layout.player_lane();
+    for (index, &x) in checkpoints.positions.iter().enumerate() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: CHECKPOINT_COLOR,
+                    custom_size: Some(Vec2::new(0.1, 3.0)),
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(
+                    x,
+                    terrain.height_at(x) + lane_layout.offset(lane) + 1.5,
+                    0.4 + lane_layout.z_offset(lane),
+                ),
+                ..Default::default()
+            })
+            .insert(DespawnWithLevel)
+            .insert(CheckpointMarker(index));
+    }
+}
+
+/// Profiled layouts get a checkpoint in the middle of every recovery gap, and after an easy section
+/// once `every_hurdles` hurdles were passed since the last checkpoint.
+fn profiled_checkpoints(layout: &ProfiledLayout, every_hurdles: usize) -> Vec<f32> {
+    let mut positions = Vec::new();
+    let mut since_checkpoint = 0;
+    for (section, next_section) in layout.sections.iter().zip(layout.sections.iter().skip(1)) {
+        since_checkpoint += section.hurdles.len();
+        let position = match section.kind {
+            SectionKind::Recovery => Some(0.5 * (section.start + section.end)),
+            SectionKind::Easy if every_hurdles <= since_checkpoint => {
+                Some(0.5 * (section.end + next_section.start))
+            }
+            SectionKind::Easy | SectionKind::Rhythm | SectionKind::Double => None,
+        };
+        if let Some(position) = position {
+            positions.push(position);
+            since_checkpoint = 0;
+        }
+    }
+    positions
+}
+
+fn update_reached_checkpoint(
+    player_status: Res<PlayerStatus>,
+    mut checkpoints: ResMut<Checkpoints>,
+    mut markers_query: Query<(&CheckpointMarker, &mut Sprite)>,
+) {
+    let passed = checkpoints
+        .positions
+        .iter()
+        .rposition(|&x| x <= player_status.distance_traveled);
+    if passed <= checkpoints.reached {
+        return;
+    }
+    checkpoints.reached = passed;
+    for (&CheckpointMarker(index), mut sprite) in markers_query.iter_mut() {
+        if Some(index) <= passed {
+            sprite.color = REACHED_CHECKPOINT_COLOR;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_player_faults(
+    mut reader: EventReader<PlayerFault>,
+    checkpoint_mode: Res<CheckpointMode>,
+    current_track: Res<CurrentTrack>,
+    mut checkpoints: ResMut<Checkpoints>,
+    mut player_status: ResMut<PlayerStatus>,
+    mut respawn_writer: EventWriter<RespawnPlayer>,
+    mut state: ResMut<State<AppState>>,
+    mut game_over_state: ResMut<State<Option<GameOver>>>,
+) {
+    let fault = if let Some(PlayerFault(fault)) = reader.iter().last() {
+        fault
+    } else {
+        return;
+    };
+    // Endless tracks are about how far you get, and what's behind the player is already gone
+    if !checkpoint_mode.0 || current_track.0.endless.is_some() {
+        if *fault == GameOver::Injured {
+            state.set(AppState::Crash).unwrap();
+        } else {
+            state.set(AppState::Menu(MenuState::GameOver)).unwrap();
+            game_over_state.set(Some(fault.clone())).unwrap();
+        }
+        return;
+    }
+    if let Some(last_respawn) = checkpoints.last_respawn {
+        if player_status.time < last_respawn + RESPAWN_GRACE {
+            return;
+        }
+    }
+    respawn_writer.send(RespawnPlayer {
+        x: checkpoints.respawn_position(),
+    });
+    checkpoints.last_respawn = Some(player_status.time);
+    player_status.respawns += 1;
+    player_status.penalty += Duration::from_secs_f32(current_track.0.checkpoints.penalty);
+}
@@ -1,10 +1,14 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
+use crate::consts::HURDLE_HEIGHT;
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::weather::Weather;
 use crate::global_types::{
-    AppState, Competitor, DespawnWithLevel, Hurdle, PlayerSprite, PlayerStatus,
+    AppState, Competitor, DespawnWithLevel, Hurdle, Lane, PlayerSprite, PlayerStatus, WaterJump,
 };
 use crate::loading::AnimationAssets;
+use crate::track::{CurrentTrack, Terrain};
 
 pub struct CompetitorsPlugin;
 
@@ -28,33 +32,51 @@ enum JumpOverNextHurdle {
     PassedAllHurdles,
 }
 
-fn add_competitors(mut commands: Commands, animation_assets: Res<AnimationAssets>) {
-    for i in 0..4 {
+fn add_competitors(
+    mut commands: Commands,
+    animation_assets: Res<AnimationAssets>,
+    current_track: Res<CurrentTrack>,
+    terrain: Res<Terrain>,
+    lane_layout: Res<LaneLayout>,
+) {
+    for (index, competitor) in current_track.0.competitors.iter().enumerate() {
+        let lane = lane_layout.competitor_lane(index);
         let mut cmd = commands.spawn();
         cmd.insert_bundle(SpriteSheetBundle {
             sprite: TextureAtlasSprite {
                 custom_size: Some(Vec2::new(2.0, 2.0)),
                 ..Default::default()
-            }
-            .into(),
+            },
             texture_atlas: animation_assets.competitor_atlas.clone(),
+            transform: Transform::from_xyz(0.0, 0.0, lane_layout.z_offset(lane)),
             ..Default::default()
         });
         cmd.insert_bundle(RigidBodyBundle {
             body_type: RigidBodyType::Dynamic.into(),
-            position: point![2.0 + i as f32 * 3.0, 1.0].into(),
+            position: point![
+                competitor.start,
+                terrain.height_at(competitor.start) + lane_layout.offset(lane) + 1.0
+            ]
+            .into(),
             ..Default::default()
         });
         cmd.insert_bundle(ColliderBundle {
             shape: ColliderShape::cuboid(0.31, 1.0).into(),
             mass_properties: MassProperties::new(Default::default(), 10.0, 0.0).into(),
+            flags: ColliderFlags {
+                collision_groups: lane_layout.runner_groups(lane),
+                solver_groups: lane_layout.runner_groups(lane),
+                ..Default::default()
+            }
+            .into(),
             ..Default::default()
         });
         cmd.insert(DespawnWithLevel);
         cmd.insert(RigidBodyPositionSync::Discrete);
         cmd.insert(Competitor);
+        cmd.insert(lane);
         cmd.insert(JumpOverNextHurdle::LookForHurdleToJumpOver);
-        cmd.insert(MaintainSpeed(3.0 + 0.7 * i as f32));
+        cmd.insert(MaintainSpeed(competitor.speed));
         cmd.insert(animation_assets.competitor.clone());
         cmd.insert(benimator::Play);
     }
@@ -63,28 +85,45 @@ fn add_competitors(mut commands: Commands, animation_assets: Res<AnimationAssets
 fn maintain_speed(
     mut runners_query: Query<(
         &MaintainSpeed,
+        &GlobalTransform,
         &mut RigidBodyVelocityComponent,
         &RigidBodyMassPropsComponent,
     )>,
+    terrain: Res<Terrain>,
+    weather: Res<Weather>,
 ) {
-    for (&MaintainSpeed(target_speed), mut velocity, mass_props) in runners_query.iter_mut() {
-        if velocity.linvel.x < target_speed {
-            let impulse = Vec2::new(target_speed - velocity.linvel.x, 0.0);
+    for (&MaintainSpeed(target_speed), transform, mut velocity, mass_props) in
+        runners_query.iter_mut()
+    {
+        let target_speed = target_speed * weather.competitor_speed_scale();
+        // Measure and push the speed along the ground, so that slopes don't slow runners down
+        let tangent = terrain.tangent_at(transform.translation.x);
+        let speed_along_ground = Vec2::from(velocity.linvel).dot(tangent);
+        if speed_along_ground < target_speed {
+            let impulse = tangent * (target_speed - speed_along_ground);
             velocity.apply_impulse(mass_props, impulse.into());
         }
     }
 }
 
+/// The impulse a competitor needs to clear a hurdle. Tuned for a standard hurdle - the vertical part
+/// scales with the square root of the height because that's how jump height scales with velocity.
+fn hurdle_jump_impulse(hurdle: &Hurdle) -> Vec2 {
+    Vec2::new(40.0, 100.0 * (hurdle.height / HURDLE_HEIGHT).sqrt())
+}
+
 fn jump_over_hurdles(
     mut jumpers_query: Query<(
         &mut JumpOverNextHurdle,
+        &Lane,
         &GlobalTransform,
         &mut RigidBodyVelocityComponent,
         &RigidBodyMassPropsComponent,
     )>,
-    hurdles_query: Query<(Entity, &GlobalTransform), With<Hurdle>>,
+    hurdles_query: Query<(Entity, &GlobalTransform, &Hurdle, &Lane)>,
+    water_jumps_query: Query<&WaterJump>,
 ) {
-    for (mut jumper, jumper_transform, mut jumper_velovity, jumper_mass_props) in
+    for (mut jumper, jumper_lane, jumper_transform, mut jumper_velovity, jumper_mass_props) in
         jumpers_query.iter_mut()
     {
         if jumper_transform.translation.x <= 0.1 {
@@ -94,15 +133,16 @@ fn jump_over_hurdles(
             JumpOverNextHurdle::LookForHurdleToJumpOver => {
                 if hurdles_query
                     .iter()
-                    .any(|(_, hurdle_transform)| hurdle_transform.translation.x <= 0.1)
+                    .any(|(_, hurdle_transform, _, _)| hurdle_transform.translation.x <= 0.1)
                 {
                     JumpOverNextHurdle::LookForHurdleToJumpOver
-                } else if let Some((hurdle_entity, _)) = hurdles_query
+                } else if let Some((hurdle_entity, _, _, _)) = hurdles_query
                     .iter()
-                    .filter(|(_, hurdle_transform)| {
-                        jumper_transform.translation.x < hurdle_transform.translation.x
+                    .filter(|(_, hurdle_transform, _, hurdle_lane)| {
+                        hurdle_lane == &jumper_lane
+                            && jumper_transform.translation.x < hurdle_transform.translation.x
                     })
-                    .min_by(|(_, t1), (_, t2)| {
+                    .min_by(|(_, t1, _, _), (_, t2, _, _)| {
                         t1.translation.x.partial_cmp(&t2.translation.x).unwrap()
                     })
                 {
@@ -112,29 +152,46 @@ fn jump_over_hurdles(
                 }
             }
             JumpOverNextHurdle::PrepareToJumpOverHurdle(entity) => {
-                let hurdle_transform = hurdles_query
-                    .get_component::<GlobalTransform>(entity)
-                    .unwrap();
-                let distance_to_entity =
-                    hurdle_transform.translation.x - jumper_transform.translation.x;
+                let (_, hurdle_transform, hurdle, _) = match hurdles_query.get(entity) {
+                    Ok(hurdle) => hurdle,
+                    // Endless tracks despawn the hurdles that are left far behind
+                    Err(_) => {
+                        *jumper = JumpOverNextHurdle::LookForHurdleToJumpOver;
+                        continue;
+                    }
+                };
+                let distance_to_entity = hurdle_transform.translation.x
+                    - 0.5 * hurdle.width
+                    - jumper_transform.translation.x;
                 if distance_to_entity < 4.0 {
-                    jumper_velovity.apply_impulse(jumper_mass_props, Vec2::new(40.0, 100.0).into());
+                    jumper_velovity
+                        .apply_impulse(jumper_mass_props, hurdle_jump_impulse(hurdle).into());
                     JumpOverNextHurdle::JumpingOverHurdle(entity)
                 } else {
                     JumpOverNextHurdle::PrepareToJumpOverHurdle(entity)
                 }
             }
             JumpOverNextHurdle::JumpingOverHurdle(entity) => {
-                let hurdle_transform = hurdles_query
-                    .get_component::<GlobalTransform>(entity)
-                    .unwrap();
-                let passed_entity_by =
-                    jumper_transform.translation.x - hurdle_transform.translation.x;
-                if passed_entity_by <= 2.0 {
+                let (_, hurdle_transform, hurdle, _) = match hurdles_query.get(entity) {
+                    Ok(hurdle) => hurdle,
+                    // Endless tracks despawn the hurdles that are left far behind
+                    Err(_) => {
+                        *jumper = JumpOverNextHurdle::LookForHurdleToJumpOver;
+                        continue;
+                    }
+                };
+                let passed_entity_by = jumper_transform.translation.x
+                    - hurdle_transform.translation.x
+                    - 0.5 * hurdle.width;
+                // Stay in the air until clearing the water pit, if there is one
+                let pit_length = water_jumps_query
+                    .get(entity)
+                    .map_or(0.0, |water_jump| water_jump.pit_length);
+                if passed_entity_by <= 2.0 + pit_length {
                     JumpOverNextHurdle::JumpingOverHurdle(entity)
                 } else {
                     jumper_velovity
-                        .apply_impulse(jumper_mass_props, Vec2::new(-40.0, -100.0).into());
+                        .apply_impulse(jumper_mass_props, (-hurdle_jump_impulse(hurdle)).into());
                     JumpOverNextHurdle::LookForHurdleToJumpOver
                 }
             }
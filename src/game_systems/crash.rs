@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::pogo::{PogoStick, StickSprite, STICK_SPRITE_INDEX};
+use crate::global_types::{
+    AppState, DespawnWithLevel, GameOver, Lane, MenuState, PlayerHead, PlayerSprite,
+};
+
+pub struct CrashPlugin;
+
+impl Plugin for CrashPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CrashTimer(Timer::from_seconds(CRASH_DURATION, false)));
+        app.add_system_set(SystemSet::on_enter(AppState::Crash).with_system(split_into_ragdoll));
+        app.add_system_set(SystemSet::on_update(AppState::Crash).with_system(end_crash));
+    }
+}
+
+/// How long the camera follows the crash before the game over menu shows up, in seconds.
+const CRASH_DURATION: f32 = 1.5;
+
+/// Where the center of the stick is, relative to the player's body.
+const STICK_OFFSET: f32 = -0.75;
+const STICK_HALF_LENGTH: f32 = 0.25;
+
+struct CrashTimer(Timer);
+
+/// Detach the stick from the player's body into a separate body, jointed at the rider's feet, so
+/// that the two tumble separately with the velocity they had when crashing.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn split_into_ragdoll(
+    mut commands: Commands,
+    mut crash_timer: ResMut<CrashTimer>,
+    lane_layout: Res<LaneLayout>,
+    mut player_query: Query<
+        (
+            Entity,
+            &RigidBodyPositionComponent,
+            &RigidBodyVelocityComponent,
+            &mut RigidBodyMassPropsComponent,
+            &Transform,
+            &Lane,
+        ),
+        With<PlayerSprite>,
+    >,
+    mut head_query: Query<
+        (
+            &mut ColliderFlagsComponent,
+            &ColliderShapeComponent,
+            &ColliderMassPropsComponent,
+            &ColliderParentComponent,
+        ),
+        With<PlayerHead>,
+    >,
+    stick_query: Query<Entity, With<PogoStick>>,
+    stick_sprite_query: Query<(Entity, &Handle<TextureAtlas>), With<StickSprite>>,
+) {
+    crash_timer.0.reset();
+
+    for entity in stick_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let mut stick_atlas = Handle::default();
+    for (entity, texture_atlas) in stick_sprite_query.iter() {
+        stick_atlas = texture_atlas.clone();
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (player_entity, position, velocity, mut mass_props, transform, &lane) in
+        player_query.iter_mut()
+    {
+        let stick_position = position.position * Isometry::translation(0.0, STICK_OFFSET);
+        // The stick keeps moving like the point of the body it was attached to
+        let lever = stick_position.translation.vector - mass_props.world_com.coords;
+        let stick_linvel = velocity.linvel + vector![-lever.y, lever.x] * velocity.angvel;
+
+        let ragdoll_groups = lane_layout.ragdoll_groups(lane);
+        for (mut flags, shape, collider_mass_props, parent) in head_query.iter_mut() {
+            flags.collision_groups = ragdoll_groups;
+            flags.solver_groups = ragdoll_groups;
+            // The mass properties were tuned for the rider and the stick together
+            mass_props.local_mprops = collider_mass_props
+                .mass_properties(&*shape.0)
+                .transform_by(&parent.pos_wrt_parent);
+            mass_props.update_world_mass_properties(&position.position);
+        }
+
+        let mut cmd = commands.spawn();
+        cmd.insert_bundle(RigidBodyBundle {
+            body_type: RigidBodyType::Dynamic.into(),
+            position: stick_position.into(),
+            velocity: RigidBodyVelocity {
+                linvel: stick_linvel,
+                angvel: velocity.angvel,
+            }
+            .into(),
+            ..Default::default()
+        });
+        cmd.insert_bundle(ColliderBundle {
+            shape: ColliderShape::cuboid(0.1, STICK_HALF_LENGTH).into(),
+            flags: ColliderFlags {
+                collision_groups: ragdoll_groups,
+                solver_groups: ragdoll_groups,
+                ..Default::default()
+            }
+            .into(),
+            material: ColliderMaterial {
+                restitution: 0.3,
+                friction: 1.0,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        });
+        cmd.insert_bundle(SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                index: STICK_SPRITE_INDEX,
+                custom_size: Some(Vec2::new(2.0, 2.0 * STICK_HALF_LENGTH)),
+                ..Default::default()
+            },
+            texture_atlas: stick_atlas.clone(),
+            transform: Transform::from_xyz(
+                stick_position.translation.x,
+                stick_position.translation.y,
+                transform.translation.z,
+            ),
+            ..Default::default()
+        });
+        cmd.insert(RigidBodyPositionSync::Discrete);
+        cmd.insert(DespawnWithLevel);
+        cmd.insert(lane);
+        let stick_entity = cmd.id();
+
+        let joint = RevoluteJoint::new()
+            .local_anchor1(point![0.0, STICK_OFFSET + STICK_HALF_LENGTH])
+            .local_anchor2(point![0.0, STICK_HALF_LENGTH]);
+        commands
+            .spawn()
+            .insert(JointBuilderComponent::new(
+                joint,
+                player_entity,
+                stick_entity,
+            ))
+            .insert(DespawnWithLevel);
+    }
+}
+
+fn end_crash(
+    time: Res<Time>,
+    mut crash_timer: ResMut<CrashTimer>,
+    mut state: ResMut<State<AppState>>,
+    mut game_over_state: ResMut<State<Option<GameOver>>>,
+) {
+    if crash_timer.0.tick(time.delta()).just_finished() {
+        state.set(AppState::Menu(MenuState::GameOver)).unwrap();
+        game_over_state.set(Some(GameOver::Injured)).unwrap();
+    }
+}
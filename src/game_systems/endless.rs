@@ -0,0 +1,265 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::prelude::SliceRandom;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::game_systems::ground::{spawn_distance_markers, spawn_ground_section, surface_patches};
+use crate::game_systems::hurdles::spawn_hurdle;
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::wind::{spawn_wind_zone, Wind};
+use crate::global_types::{AppState, AssistLevel, MenuState, PlayerStatus, RngStream, TrackSeed};
+use crate::loading::TextureAssets;
+use crate::track::{
+    CurrentTrack, EndlessSettings, HurdleDefinition, HurdleDimensions, HurdleRules,
+    LoadCurrentTrack, SurfacePatch, Terrain, WindForce, WindZone,
+};
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BestDistance>();
+        app.add_system_set(
+            SystemSet::on_enter(AppState::ClearLevelAndThenLoad)
+                .with_system(reset_endless_track.after(LoadCurrentTrack)),
+        );
+        app.add_system_set(
+            SystemSet::on_enter(AppState::LoadLevel).with_system(stream_endless_track),
+        );
+        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(stream_endless_track));
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Menu(MenuState::GameOver))
+                .with_system(record_best_distance),
+        );
+    }
+}
+
+/// The farthest the player got on an endless track. Kept separately for each assist level.
+#[derive(Default)]
+pub struct BestDistance {
+    distances: HashMap<AssistLevel, f32>,
+    /// Whether the last run set the record for its assist level.
+    pub new_record: bool,
+}
+
+impl BestDistance {
+    pub fn distance(&self, assist_level: AssistLevel) -> f32 {
+        self.distances.get(&assist_level).copied().unwrap_or(0.0)
+    }
+}
+
+struct Chunk {
+    end: f32,
+    entities: Vec<Entity>,
+}
+
+/// The generation state of the current endless track. Chunks are always generated in order from
+/// the same RNG, so the same seed always generates the same track.
+struct EndlessTrack {
+    settings: EndlessSettings,
+    hurdle_rules: HurdleRules,
+    rng: StdRng,
+    patches: Vec<SurfacePatch>,
+    generated_until: f32,
+    next_hurdle_at: f32,
+    chunks: VecDeque<Chunk>,
+}
+
+impl EndlessTrack {
+    /// Goes from 0 at the start line to 1 at `ramp_distance`.
+    fn difficulty_at(&self, x: f32) -> f32 {
+        (x / self.settings.ramp_distance).clamp(0.0, 1.0)
+    }
+
+    /// Generate the hurdle at `next_hurdle_at`, and pick where the one after it goes.
+    fn next_hurdle(&mut self) -> HurdleDefinition {
+        let position = self.next_hurdle_at;
+        let difficulty = self.difficulty_at(position);
+        let entry = self
+            .settings
+            .mix
+            .choose_weighted(&mut self.rng, |entry| entry.weight)
+            .ok()
+            .cloned();
+        let dimensions = entry
+            .as_ref()
+            .map(|entry| entry.dimensions)
+            .unwrap_or_default();
+        let height_scale = 1.0 + (self.settings.max_height_scale - 1.0) * difficulty;
+        let hurdle = HurdleDefinition {
+            position,
+            dimensions: HurdleDimensions {
+                width: dimensions.width,
+                height: dimensions.height * height_scale,
+            },
+            motion: entry.as_ref().and_then(|entry| entry.motion),
+            water_pit: entry.as_ref().and_then(|entry| entry.water_pit),
+            overhead_bar: entry.as_ref().and_then(|entry| entry.overhead_bar),
+        };
+
+        let spacing = self.settings.start_spacing
+            + (self.settings.min_spacing - self.settings.start_spacing) * difficulty;
+        let attached_length = hurdle.cleared_at() - position;
+        self.next_hurdle_at += attached_length + spacing * self.rng.gen_range(1.0..1.5);
+        hurdle
+    }
+
+    /// Half of the chunks get a wind zone over them, blowing harder the higher the difficulty.
+    fn next_wind_zone(&mut self, chunk_start: f32, chunk_end: f32) -> Option<WindZone> {
+        let max_wind = self.settings.max_wind * self.difficulty_at(chunk_start);
+        if max_wind <= 0.0 || !self.rng.gen_bool(0.5) {
+            return None;
+        }
+        Some(WindZone {
+            start: chunk_start,
+            end: chunk_end,
+            force: WindForce {
+                x: self.rng.gen_range(-max_wind..=max_wind),
+                y: 0.0,
+            },
+        })
+    }
+
+    /// Extend the terrain to cover the chunk, with a random slope that gets steeper with the
+    /// difficulty.
+    fn extend_terrain(&mut self, terrain: &mut Terrain, chunk_end: f32) {
+        let last = *terrain
+            .points
+            .last()
+            .expect("the terrain always has points");
+        if chunk_end <= last.x {
+            return;
+        }
+        let max_height = self.settings.max_hill_height * self.difficulty_at(chunk_end);
+        let height = if 0.0 < max_height {
+            self.rng.gen_range(-max_height..max_height)
+        } else {
+            last.y
+        };
+        terrain.points.push(Vec2::new(chunk_end, height));
+    }
+}
+
+fn reset_endless_track(
+    mut commands: Commands,
+    current_track: Res<CurrentTrack>,
+    track_seed: Res<TrackSeed>,
+) {
+    let track = &current_track.0;
+    if let Some(settings) = &track.endless {
+        commands.insert_resource(EndlessTrack {
+            settings: settings.clone(),
+            hurdle_rules: track.hurdle_rules,
+            rng: track_seed.rng(RngStream::EndlessTrack),
+            patches: surface_patches(track, &track_seed),
+            generated_until: track.bounds.left,
+            next_hurdle_at: settings.before_first,
+            chunks: VecDeque::new(),
+        });
+    } else {
+        commands.remove_resource::<EndlessTrack>();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn stream_endless_track(
+    mut commands: Commands,
+    endless_track: Option<ResMut<EndlessTrack>>,
+    player_status: Res<PlayerStatus>,
+    mut terrain: ResMut<Terrain>,
+    mut wind: ResMut<Wind>,
+    lane_layout: Res<LaneLayout>,
+    texture_assets: Res<TextureAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut endless_track = if let Some(endless_track) = endless_track {
+        endless_track
+    } else {
+        return;
+    };
+    let player_x = player_status.distance_traveled;
+    let view_distance = endless_track.settings.view_distance;
+
+    while endless_track.generated_until < player_x + view_distance {
+        let chunk_start = endless_track.generated_until;
+        let chunk_end = chunk_start + endless_track.settings.chunk_length;
+        endless_track.extend_terrain(&mut terrain, chunk_end);
+
+        let mut entities = spawn_ground_section(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &terrain,
+            &lane_layout,
+            &endless_track.patches,
+            chunk_start..chunk_end,
+        );
+        entities.extend(spawn_distance_markers(
+            &mut commands,
+            &terrain,
+            &lane_layout,
+            chunk_start.max(1.0)..chunk_end,
+        ));
+        while endless_track.next_hurdle_at < chunk_end {
+            let hurdle = endless_track.next_hurdle();
+            entities.extend(spawn_hurdle(
+                &mut commands,
+                &texture_assets,
+                &terrain,
+                &lane_layout,
+                endless_track.hurdle_rules,
+                &hurdle,
+            ));
+        }
+        if let Some(zone) = endless_track.next_wind_zone(chunk_start, chunk_end) {
+            entities.push(spawn_wind_zone(
+                &mut commands,
+                &terrain,
+                &lane_layout,
+                &zone,
+            ));
+            wind.zones.push(zone);
+        }
+
+        endless_track.chunks.push_back(Chunk {
+            end: chunk_end,
+            entities,
+        });
+        endless_track.generated_until = chunk_end;
+    }
+
+    while let Some(chunk) = endless_track.chunks.front() {
+        if player_x - view_distance < chunk.end {
+            break;
+        }
+        for &entity in chunk.entities.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        terrain.trim_before(chunk.end);
+        endless_track.chunks.pop_front();
+    }
+    wind.zones
+        .retain(|zone| player_x - view_distance < zone.end);
+}
+
+fn record_best_distance(
+    current_track: Res<CurrentTrack>,
+    player_status: Res<PlayerStatus>,
+    mut best_distance: ResMut<BestDistance>,
+) {
+    if current_track.0.endless.is_none() {
+        return;
+    }
+    let assist_level = player_status.assist_level;
+    best_distance.new_record =
+        best_distance.distance(assist_level) < player_status.distance_traveled;
+    if best_distance.new_record {
+        best_distance
+            .distances
+            .insert(assist_level, player_status.distance_traveled);
+    }
+}
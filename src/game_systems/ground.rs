@@ -1,8 +1,21 @@
+use std::ops::Range;
+
 use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::utils::HashMap;
 use bevy_rapier2d::prelude::*;
+use rand::prelude::SliceRandom;
 
+use crate::game_systems::checkpoints::PlayerFault;
+use crate::game_systems::hurdles::distribute_distances;
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::power_ups::{ActivePowerUps, Hazard};
 use crate::global_types::{
-    AppState, DespawnWithLevel, GameBoundaries, GameOver, Ground, MenuState, PlayerHead,
+    AppState, DespawnWithLevel, GameOver, Ground, PlayerHead, RngStream, TrackSeed,
+};
+use crate::track::{
+    CurrentTrack, SurfaceLayout, SurfacePatch, SurfaceType, Terrain, TrackDefinition,
 };
 use crate::utils::entities_ordered_by_type;
 
@@ -15,57 +28,307 @@ impl Plugin for GroundPlugin {
     }
 }
 
-fn add_ground(mut commands: Commands, game_boundaries: Res<GameBoundaries>) {
-    let mut cmd = commands.spawn_bundle(RigidBodyBundle {
-        body_type: RigidBodyType::Static.into(),
-        ..Default::default()
-    });
-    cmd.insert_bundle(ColliderBundle {
-        shape: ColliderShape::cuboid(game_boundaries.width() * 0.5, 1.0).into(),
-        position: Vec2::new(game_boundaries.center(), -1.0).into(),
-        ..Default::default()
-    });
-    cmd.insert(ColliderPositionSync::Discrete);
-    cmd.insert(DespawnWithLevel);
-    cmd.insert(Ground);
-
-    commands.spawn_bundle(SpriteBundle {
-        sprite: Sprite {
-            color: Color::rgb_u8(185, 113, 124),
-            custom_size: Some(Vec2::new(game_boundaries.width(), 2.0)),
-            ..Default::default()
-        },
-        transform: Transform {
-            translation: Vec3::new(game_boundaries.center(), 0.0, -0.1),
-            ..Default::default()
-        },
-        ..Default::default()
-    });
+/// How far below the terrain surface the ground goes.
+const GROUND_DEPTH: f32 = 2.0;
 
-    let every = 1.0;
-    let how_many = (game_boundaries.width() / every) as u32;
-    for i in 1..how_many {
-        commands.spawn_bundle(SpriteBundle {
-            sprite: Sprite {
-                color: Color::YELLOW,
-                custom_size: Some(Vec2::new(0.5, 0.1)),
+/// How thick the colored layer of special surfaces is.
+const SURFACE_LAYER_THICKNESS: f32 = 0.25;
+
+/// The material of special surfaces. They use the `Multiply` combine rule, so their values act as
+/// factors on the material of whatever touches them.
+fn surface_material(surface: SurfaceType) -> ColliderMaterial {
+    let (friction, restitution) = match surface {
+        SurfaceType::Normal => return ColliderMaterial::default(),
+        SurfaceType::Trampoline => (0.75, 0.65),
+        SurfaceType::Mud => (1.5, 0.3),
+        SurfaceType::Ice => (0.05, 0.5),
+    };
+    ColliderMaterial {
+        friction,
+        restitution,
+        friction_combine_rule: CoefficientCombineRule::Multiply,
+        restitution_combine_rule: CoefficientCombineRule::Multiply,
+    }
+}
+
+fn surface_color(surface: SurfaceType) -> Color {
+    match surface {
+        SurfaceType::Normal => Color::rgb_u8(185, 113, 124),
+        SurfaceType::Trampoline => Color::rgb_u8(64, 200, 96),
+        SurfaceType::Mud => Color::rgb_u8(110, 78, 48),
+        SurfaceType::Ice => Color::rgb_u8(190, 235, 250),
+    }
+}
+
+/// The special surface patches of the track. Generated patches are only spread over the track
+/// bounds.
+pub fn surface_patches(track: &TrackDefinition, track_seed: &TrackSeed) -> Vec<SurfacePatch> {
+    match &track.surfaces {
+        SurfaceLayout::Explicit(patches) => patches.clone(),
+        SurfaceLayout::Generated {
+            before_first,
+            patch_length,
+            count,
+            mix,
+        } => {
+            let starts = distribute_distances(
+                &mut track_seed.rng(RngStream::SurfacePlacement),
+                *count,
+                *before_first..track.bounds.right,
+                // Leave at least one patch length of normal surface between patches
+                patch_length * 2.0,
+            );
+            let mut rng = track_seed.rng(RngStream::SurfaceTypes);
+            starts
+                .into_iter()
+                .filter_map(|start| {
+                    let entry = mix.choose_weighted(&mut rng, |entry| entry.weight).ok()?;
+                    Some(SurfacePatch {
+                        start,
+                        end: start + patch_length,
+                        surface: entry.surface,
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+fn add_ground(
+    mut commands: Commands,
+    terrain: Res<Terrain>,
+    current_track: Res<CurrentTrack>,
+    track_seed: Res<TrackSeed>,
+    lane_layout: Res<LaneLayout>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let track = &current_track.0;
+    if track.endless.is_some() {
+        // Endless tracks stream their ground as the player advances
+        return;
+    }
+    let patches = surface_patches(track, &track_seed);
+    let bounds = track.bounds.left..track.bounds.right;
+    spawn_ground_section(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &terrain,
+        &lane_layout,
+        &patches,
+        bounds.clone(),
+    );
+    spawn_distance_markers(
+        &mut commands,
+        &terrain,
+        &lane_layout,
+        (bounds.start + 1.0)..bounds.end,
+    );
+}
+
+/// Spawn the ground of every lane between `range.start` and `range.end`. The terrain must already
+/// cover that range.
+pub fn spawn_ground_section(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    terrain: &Terrain,
+    lane_layout: &LaneLayout,
+    patches: &[SurfacePatch],
+    range: Range<f32>,
+) -> Vec<Entity> {
+    let surface_at = |x: f32| {
+        patches
+            .iter()
+            .find(|patch| patch.start <= x && x < patch.end)
+            .map_or(SurfaceType::Normal, |patch| patch.surface)
+    };
+    let mut pieces = Vec::new();
+    let mut terrain_quads = Vec::new();
+    let mut surface_layers = HashMap::<SurfaceType, Vec<[Vec2; 4]>>::default();
+    let bottom = terrain.lowest_point() - GROUND_DEPTH;
+
+    for (start, end) in terrain.segments() {
+        if end.x <= range.start || range.end <= start.x {
+            continue;
+        }
+        let point_at = |x: f32| start.lerp(end, (x - start.x) / (end.x - start.x));
+        let (start, end) = (
+            point_at(start.x.max(range.start)),
+            point_at(end.x.min(range.end)),
+        );
+        terrain_quads.push([
+            start,
+            end,
+            Vec2::new(end.x, bottom),
+            Vec2::new(start.x, bottom),
+        ]);
+
+        // Split the segment wherever the surface changes
+        let mut cuts = vec![start.x];
+        cuts.extend(
+            patches
+                .iter()
+                .flat_map(|patch| [patch.start, patch.end])
+                .filter(|&x| start.x < x && x < end.x),
+        );
+        cuts.push(end.x);
+        cuts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        for (&piece_start, &piece_end) in cuts.iter().zip(cuts.iter().skip(1)) {
+            if piece_end <= piece_start {
+                continue;
+            }
+            let surface = surface_at(0.5 * (piece_start + piece_end));
+            let top_left = point_at(piece_start);
+            let top_right = point_at(piece_end);
+            let bottom = top_left.y.min(top_right.y) - GROUND_DEPTH;
+            let shape = ColliderShape::convex_hull(&[
+                top_left.into(),
+                top_right.into(),
+                point![top_right.x, bottom],
+                point![top_left.x, bottom],
+            ])
+            .expect("terrain segments always have an area");
+            pieces.push((shape, surface));
+
+            if surface != SurfaceType::Normal {
+                let thickness = Vec2::new(0.0, SURFACE_LAYER_THICKNESS);
+                surface_layers.entry(surface).or_default().push([
+                    top_left,
+                    top_right,
+                    top_right - thickness,
+                    top_left - thickness,
+                ]);
+            }
+        }
+    }
+
+    let mut visuals = vec![(
+        meshes.add(quads_mesh(&terrain_quads)),
+        materials.add(surface_color(SurfaceType::Normal).into()),
+        -0.1,
+    )];
+    visuals.extend(surface_layers.into_iter().map(|(surface, quads)| {
+        (
+            meshes.add(quads_mesh(&quads)),
+            materials.add(surface_color(surface).into()),
+            -0.05,
+        )
+    }));
+
+    let mut entities = Vec::new();
+    for lane in lane_layout.lanes() {
+        let offset = lane_layout.offset(lane);
+        for (shape, surface) in pieces.iter() {
+            let mut cmd = commands.spawn_bundle(RigidBodyBundle {
+                body_type: RigidBodyType::Static.into(),
                 ..Default::default()
-            },
-            transform: Transform {
-                translation: Vec3::new(game_boundaries.left + every * i as f32, -0.05, 0.5),
+            });
+            cmd.insert_bundle(ColliderBundle {
+                shape: shape.clone().into(),
+                position: Vec2::new(0.0, offset).into(),
+                material: surface_material(*surface).into(),
+                flags: ColliderFlags {
+                    collision_groups: lane_layout.ground_groups(lane),
+                    ..Default::default()
+                }
+                .into(),
                 ..Default::default()
-            },
-            ..Default::default()
-        });
+            });
+            cmd.insert(ColliderPositionSync::Discrete);
+            cmd.insert(DespawnWithLevel);
+            cmd.insert(Ground);
+            cmd.insert(lane);
+            entities.push(cmd.id());
+        }
+        for (mesh, material, z) in visuals.iter() {
+            let entity = commands
+                .spawn_bundle(ColorMesh2dBundle {
+                    mesh: mesh.clone().into(),
+                    material: material.clone(),
+                    transform: Transform {
+                        translation: Vec3::new(0.0, offset, z + lane_layout.z_offset(lane)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(DespawnWithLevel)
+                .id();
+            entities.push(entity);
+        }
+    }
+    entities
+}
+
+/// Spawn a marker at every meter between `range.start` and `range.end`. Distance markers are only
+/// drawn on the player's lane.
+pub fn spawn_distance_markers(
+    commands: &mut Commands,
+    terrain: &Terrain,
+    lane_layout: &LaneLayout,
+    range: Range<f32>,
+) -> Vec<Entity> {
+    let marker_offset = lane_layout.offset(lane_layout.player_lane());
+    let marker_z = 0.5 + lane_layout.z_offset(lane_layout.player_lane());
+    let every = 1.0;
+    let first = (range.start / every).ceil() as i32;
+    let last = (range.end / every).ceil() as i32;
+    (first..last)
+        .map(|i| {
+            let x = every * i as f32;
+            let tangent = terrain.tangent_at(x);
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::YELLOW,
+                        custom_size: Some(Vec2::new(0.5, 0.1)),
+                        ..Default::default()
+                    },
+                    transform: Transform {
+                        translation: Vec3::new(
+                            x,
+                            terrain.height_at(x) + marker_offset - 0.05,
+                            marker_z,
+                        ),
+                        rotation: Quat::from_rotation_z(tangent.y.atan2(tangent.x)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(DespawnWithLevel)
+                .id()
+        })
+        .collect()
+}
+
+/// Build a mesh from quads given as top-left, top-right, bottom-right, bottom-left.
+fn quads_mesh(quads: &[[Vec2; 4]]) -> Mesh {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for quad in quads {
+        let base = positions.len() as u32;
+        positions.extend(quad.iter().map(|corner| [corner.x, corner.y, 0.0]));
+        indices.extend([0, 3, 2, 0, 2, 1].map(|i| base + i));
     }
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![[0.0, 0.0, 1.0]; positions.len()],
+    );
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; positions.len()]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
 }
 
 fn detect_ground_touch(
     mut reader: EventReader<ContactEvent>,
     ground_query: Query<(), With<Ground>>,
     player_head_query: Query<(), With<PlayerHead>>,
-    mut state: ResMut<State<AppState>>,
-    mut game_over_state: ResMut<State<Option<GameOver>>>,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+    mut fault_writer: EventWriter<PlayerFault>,
 ) {
     for event in reader.iter() {
         if let ContactEvent::Started(handle1, handle2) = event {
@@ -75,10 +338,9 @@ fn detect_ground_touch(
                 player_head_query
             )
             .is_some()
+                && !active_power_ups.protect_from(Hazard::HeadOnGround)
             {
-                state.set(AppState::Menu(MenuState::GameOver)).unwrap();
-                game_over_state.set(Some(GameOver::Injured)).unwrap();
-            } else {
+                fault_writer.send(PlayerFault(GameOver::Injured));
             }
         }
     }
@@ -0,0 +1,310 @@
+use std::ops::Range;
+
+use rand::prelude::SliceRandom;
+use rand::Rng;
+
+use crate::game_systems::hurdles::hurdle_from_mix;
+use crate::global_types::{RngStream, TrackSeed};
+use crate::track::{DifficultyProfile, HurdleDefinition, TrackDefinition};
+use crate::utils::random_between;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    /// A single hurdle with a wide gap after it.
+    Easy,
+    /// Evenly spaced hurdles.
+    Rhythm,
+    /// Two hurdles with the smallest allowed gap between them.
+    Double,
+    /// The long gap after a hard section. Has no hurdles.
+    Recovery,
+}
+
+/// A part of a profiled layout. Sections go from their first hurdle to where their last hurdle is
+/// cleared - except for recovery sections, which are the gap between two sections.
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub kind: SectionKind,
+    pub start: f32,
+    pub end: f32,
+    /// Indices into the layout's hurdles.
+    pub hurdles: Range<usize>,
+}
+
+pub struct ProfiledLayout {
+    pub hurdles: Vec<HurdleDefinition>,
+    pub sections: Vec<Section>,
+}
+
+/// The profiled layout of the track. Always the same for the same track seed.
+pub fn profiled_layout(
+    profile: &DifficultyProfile,
+    track: &TrackDefinition,
+    track_seed: &TrackSeed,
+) -> ProfiledLayout {
+    generate_profiled_layout(
+        profile,
+        track.bounds.right,
+        &mut track_seed.rng(RngStream::HurdlePlacement),
+        &mut track_seed.rng(RngStream::HurdleDimensions),
+    )
+}
+
+/// Place hurdles according to the profile, up to `track_end`. Section kinds and gaps come from
+/// `placement_rng` and hurdle dimensions from `dimensions_rng`, so changing the mix does not move
+/// the hurdles around.
+pub fn generate_profiled_layout(
+    profile: &DifficultyProfile,
+    track_end: f32,
+    placement_rng: &mut impl Rng,
+    dimensions_rng: &mut impl Rng,
+) -> ProfiledLayout {
+    let min_gap = profile.min_gap.max(0.0);
+    let max_gap = profile.max_gap.max(min_gap);
+    let mid_gap = 0.5 * (min_gap + max_gap);
+    let length = track_end - profile.before_first;
+    let mut layout = ProfiledLayout {
+        hurdles: Vec::new(),
+        sections: Vec::new(),
+    };
+
+    let mut position = profile.before_first;
+    while position < track_end {
+        let difficulty = ((position - profile.before_first) / length).clamp(0.0, 1.0);
+        let kind = if position < profile.warmup {
+            SectionKind::Easy
+        } else {
+            [
+                (SectionKind::Easy, 1.0 - difficulty),
+                (SectionKind::Rhythm, profile.rhythm_weight * difficulty),
+                (SectionKind::Double, profile.double_weight * difficulty),
+            ]
+            .choose_weighted(placement_rng, |(_, weight)| *weight)
+            .map_or(SectionKind::Easy, |(kind, _)| *kind)
+        };
+        let (count, gap) = match kind {
+            SectionKind::Easy => (1, 0.0),
+            SectionKind::Rhythm => (
+                placement_rng.gen_range(3..=5),
+                random_between(
+                    placement_rng,
+                    min_gap,
+                    mid_gap + (min_gap - mid_gap) * difficulty,
+                ),
+            ),
+            SectionKind::Double => (2, min_gap),
+            SectionKind::Recovery => unreachable!("recovery sections are only added after others"),
+        };
+
+        let first = layout.hurdles.len();
+        for _ in 0..count {
+            if let Some(previous) = layout.hurdles[first..].last() {
+                position = previous.cleared_at() + gap;
+            }
+            if track_end <= position {
+                break;
+            }
+            layout
+                .hurdles
+                .push(hurdle_from_mix(position, &profile.mix, dimensions_rng));
+        }
+        let last = match layout.hurdles[first..].last() {
+            Some(last) => last,
+            None => break,
+        };
+        let end = last.cleared_at();
+        layout.sections.push(Section {
+            kind,
+            start: layout.hurdles[first].position,
+            end,
+            hurdles: first..layout.hurdles.len(),
+        });
+
+        let gap_after = if kind == SectionKind::Easy {
+            random_between(
+                placement_rng,
+                max_gap + (mid_gap - max_gap) * difficulty,
+                max_gap,
+            )
+        } else {
+            let recovery = random_between(placement_rng, 0.5 * (mid_gap + max_gap), max_gap);
+            layout.sections.push(Section {
+                kind: SectionKind::Recovery,
+                start: end,
+                end: end + recovery,
+                hurdles: layout.hurdles.len()..layout.hurdles.len(),
+            });
+            recovery
+        };
+        position = end + gap_after;
+    }
+
+    // There is nothing to recover for after the last hurdle
+    if let Some(Section {
+        kind: SectionKind::Recovery,
+        ..
+    }) = layout.sections.last()
+    {
+        layout.sections.pop();
+    }
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track::{
+        HurdleDimensions, HurdleMixEntry, OverheadBarDefinition, WaterPitDefinition,
+    };
+
+    const TRACK_END: f32 = 400.0;
+    const EPSILON: f32 = 1e-3;
+
+    fn profile(mix: Vec<HurdleMixEntry>) -> DifficultyProfile {
+        DifficultyProfile {
+            before_first: 15.0,
+            min_gap: 3.0,
+            max_gap: 20.0,
+            warmup: 60.0,
+            rhythm_weight: 1.0,
+            double_weight: 0.5,
+            mix,
+        }
+    }
+
+    fn mix_with_attachments() -> Vec<HurdleMixEntry> {
+        vec![
+            HurdleMixEntry {
+                dimensions: HurdleDimensions::default(),
+                motion: None,
+                water_pit: None,
+                overhead_bar: None,
+                weight: 3.0,
+            },
+            HurdleMixEntry {
+                dimensions: HurdleDimensions {
+                    width: 0.5,
+                    height: 1.2,
+                },
+                motion: None,
+                water_pit: Some(WaterPitDefinition::default()),
+                overhead_bar: None,
+                weight: 1.0,
+            },
+            HurdleMixEntry {
+                dimensions: HurdleDimensions::default(),
+                motion: None,
+                water_pit: None,
+                overhead_bar: Some(OverheadBarDefinition::default()),
+                weight: 1.0,
+            },
+        ]
+    }
+
+    fn generate(profile: &DifficultyProfile, seed: u64) -> ProfiledLayout {
+        let track_seed = TrackSeed(seed);
+        generate_profiled_layout(
+            profile,
+            TRACK_END,
+            &mut track_seed.rng(RngStream::HurdlePlacement),
+            &mut track_seed.rng(RngStream::HurdleDimensions),
+        )
+    }
+
+    fn gaps(hurdles: &[HurdleDefinition]) -> impl '_ + Iterator<Item = f32> {
+        hurdles
+            .iter()
+            .zip(hurdles.iter().skip(1))
+            .map(|(previous, next)| next.position - previous.cleared_at())
+    }
+
+    #[test]
+    fn gaps_stay_within_bounds() {
+        for mix in [Vec::new(), mix_with_attachments()] {
+            let profile = profile(mix);
+            for seed in 0..500 {
+                let layout = generate(&profile, seed);
+                assert!(!layout.hurdles.is_empty(), "seed {}", seed);
+                for gap in gaps(&layout.hurdles) {
+                    assert!(
+                        profile.min_gap - EPSILON <= gap && gap <= profile.max_gap + EPSILON,
+                        "seed {}: gap of {}",
+                        seed,
+                        gap
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hurdles_stay_on_the_track() {
+        let profile = profile(mix_with_attachments());
+        for seed in 0..500 {
+            let layout = generate(&profile, seed);
+            assert_eq!(layout.hurdles[0].position, profile.before_first);
+            for hurdle in layout.hurdles.iter() {
+                assert!(hurdle.position < TRACK_END, "seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn sections_describe_the_hurdles() {
+        let profile = profile(mix_with_attachments());
+        for seed in 0..500 {
+            let layout = generate(&profile, seed);
+            let mut next_hurdle = 0;
+            let mut previous_kind = None;
+            for section in layout.sections.iter() {
+                assert_eq!(section.hurdles.start, next_hurdle, "seed {}", seed);
+                next_hurdle = section.hurdles.end;
+                match section.kind {
+                    SectionKind::Easy => assert_eq!(section.hurdles.len(), 1),
+                    SectionKind::Rhythm => {
+                        let hurdles = &layout.hurdles[section.hurdles.clone()];
+                        let first_gap = gaps(hurdles).next();
+                        for gap in gaps(hurdles) {
+                            assert!((gap - first_gap.unwrap()).abs() < EPSILON, "seed {}", seed);
+                        }
+                    }
+                    SectionKind::Double => {
+                        for gap in gaps(&layout.hurdles[section.hurdles.clone()]) {
+                            assert!((gap - profile.min_gap).abs() < EPSILON, "seed {}", seed);
+                        }
+                    }
+                    SectionKind::Recovery => {
+                        assert!(section.hurdles.is_empty());
+                        assert!(matches!(
+                            previous_kind,
+                            Some(SectionKind::Rhythm | SectionKind::Double)
+                        ));
+                    }
+                }
+                if section.start < profile.warmup {
+                    assert_eq!(section.kind, SectionKind::Easy, "seed {}", seed);
+                }
+                previous_kind = Some(section.kind);
+            }
+            assert_eq!(next_hurdle, layout.hurdles.len(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn same_seed_same_layout() {
+        let profile = profile(mix_with_attachments());
+        for seed in 0..50 {
+            let positions = |layout: ProfiledLayout| {
+                layout
+                    .hurdles
+                    .iter()
+                    .map(|hurdle| hurdle.position)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                positions(generate(&profile, seed)),
+                positions(generate(&profile, seed))
+            );
+        }
+    }
+}
@@ -1,15 +1,29 @@
 use bevy::prelude::*;
+use bevy_rapier2d::physics::PhysicsSystems;
 use bevy_rapier2d::prelude::*;
 use std::ops::Range;
+use std::time::Duration;
 
 use rand::prelude::SliceRandom;
 use rand::Rng;
 
-use crate::consts::{BEFORE_FIRST, HURDLE_HEIGHT, HURDLE_SPACING, HURDLE_WIDTH};
+use crate::consts::{HURDLE_HEIGHT, HURDLE_WIDTH};
+use crate::game_systems::checkpoints::PlayerFault;
+use crate::game_systems::hurdle_profile::profiled_layout;
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::obstacles::add_obstacle_motion;
+use crate::game_systems::overhead_bars::spawn_overhead_bar;
+use crate::game_systems::power_ups::{ActivePowerUps, Hazard};
+use crate::game_systems::water::spawn_water_pit;
 use crate::global_types::{
-    AppState, DespawnWithLevel, GameBoundaries, GameOver, Hurdle, MenuState, Player,
+    AppState, DespawnWithLevel, GameOver, Hurdle, Player, PlayerHead, PlayerSprite, PlayerStatus,
+    RngStream, TrackSeed, WaterJump,
 };
 use crate::loading::TextureAssets;
+use crate::track::{
+    CurrentTrack, HurdleDefinition, HurdleDimensions, HurdleLayout, HurdleMixEntry, HurdleRules,
+    Terrain, TrackDefinition,
+};
 use crate::utils::entities_ordered_by_type;
 
 pub struct HurdlesPlugin;
@@ -17,11 +31,21 @@ pub struct HurdlesPlugin;
 impl Plugin for HurdlesPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(AppState::LoadLevel).with_system(add_hurdles));
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(detect_hurdle_touch));
+        app.init_resource::<PlayerSpeedBeforeStep>();
+        app.add_system_set({
+            SystemSet::on_update(AppState::Game)
+                .with_system(record_player_speed.before(PhysicsSystems::StepWorld))
+                .with_system(detect_hurdle_touch.after(PhysicsSystems::StepWorld))
+        });
     }
 }
 
-fn distribute_distances(max_num: usize, over_range: Range<f32>, min_size: f32) -> Vec<f32> {
+pub fn distribute_distances(
+    rng: &mut impl Rng,
+    max_num: usize,
+    over_range: Range<f32>,
+    min_size: f32,
+) -> Vec<f32> {
     if max_num == 0 {
         return Vec::new();
     }
@@ -41,12 +65,12 @@ fn distribute_distances(max_num: usize, over_range: Range<f32>, min_size: f32) -
         let cut_at = if cut_range.is_empty() {
             min_size
         } else {
-            rand::thread_rng().gen_range(cut_range)
+            rng.gen_range(cut_range)
         };
         distances.push(cut_at);
         distances.push(to_cut - cut_at);
     }
-    distances.shuffle(&mut rand::thread_rng());
+    distances.shuffle(rng);
     let mut so_far = over_range.start;
     let num_to_return = distances.len() - 1;
     distances
@@ -59,60 +83,277 @@ fn distribute_distances(max_num: usize, over_range: Range<f32>, min_size: f32) -
         .collect()
 }
 
+/// The hurdles of each lane. Generated layouts are always the same for the same track seed.
+pub fn hurdle_definitions(
+    track: &TrackDefinition,
+    track_seed: &TrackSeed,
+) -> Vec<HurdleDefinition> {
+    match &track.hurdles {
+        HurdleLayout::Explicit(hurdles) => hurdles.clone(),
+        HurdleLayout::Generated {
+            before_first,
+            spacing,
+            mix,
+        } => {
+            // Leave room for the longest water pit or overhead bar the mix can attach to a hurdle,
+            // so that it doesn't run into the next hurdle
+            let attached_length = mix
+                .iter()
+                .map(|entry| entry.hurdle_at(0.0).cleared_at())
+                .fold(0.0, f32::max);
+            let min_distance = spacing + attached_length;
+            let allowed_width = track.bounds.right - before_first;
+            let placements = distribute_distances(
+                &mut track_seed.rng(RngStream::HurdlePlacement),
+                (allowed_width / min_distance) as usize,
+                *before_first..track.bounds.right,
+                min_distance,
+            );
+            let mut rng = track_seed.rng(RngStream::HurdleDimensions);
+            placements
+                .into_iter()
+                .map(|position| hurdle_from_mix(position, mix, &mut rng))
+                .collect()
+        }
+        HurdleLayout::Profiled(profile) => profiled_layout(profile, track, track_seed).hurdles,
+    }
+}
+
+/// A hurdle at `position`, picked randomly from `mix` according to the weights. An empty `mix`
+/// means a standard hurdle.
+pub fn hurdle_from_mix(
+    position: f32,
+    mix: &[HurdleMixEntry],
+    rng: &mut impl Rng,
+) -> HurdleDefinition {
+    match mix.choose_weighted(rng, |entry| entry.weight) {
+        Ok(entry) => entry.hurdle_at(position),
+        Err(_) => HurdleDefinition {
+            position,
+            dimensions: Default::default(),
+            motion: None,
+            water_pit: None,
+            overhead_bar: None,
+        },
+    }
+}
+
 fn add_hurdles(
     mut commands: Commands,
-    game_boundaries: Res<GameBoundaries>,
     texture_assets: Res<TextureAssets>,
+    track_seed: Res<TrackSeed>,
+    current_track: Res<CurrentTrack>,
+    terrain: Res<Terrain>,
+    lane_layout: Res<LaneLayout>,
 ) {
-    let allowed_width = game_boundaries.right - BEFORE_FIRST;
-    let placements = distribute_distances(
-        (allowed_width / HURDLE_SPACING) as usize,
-        BEFORE_FIRST..game_boundaries.right,
-        HURDLE_SPACING,
-    );
+    for hurdle in hurdle_definitions(&current_track.0, &track_seed) {
+        spawn_hurdle(
+            &mut commands,
+            &texture_assets,
+            &terrain,
+            &lane_layout,
+            current_track.0.hurdle_rules,
+            &hurdle,
+        );
+    }
+}
 
-    for placement in placements {
-        let mut cmd = commands.spawn_bundle(RigidBodyBundle {
-            body_type: RigidBodyType::Dynamic.into(),
-            position: point![placement, HURDLE_HEIGHT * 0.5].into(),
+/// Spawn the hurdle in every lane, together with everything attached to it.
+pub fn spawn_hurdle(
+    commands: &mut Commands,
+    texture_assets: &TextureAssets,
+    terrain: &Terrain,
+    lane_layout: &LaneLayout,
+    hurdle_rules: HurdleRules,
+    hurdle: &HurdleDefinition,
+) -> Vec<Entity> {
+    let &HurdleDefinition {
+        position,
+        dimensions: HurdleDimensions { width, height },
+        motion,
+        water_pit,
+        overhead_bar,
+    } = hurdle;
+    let mut entities = Vec::new();
+    for lane in lane_layout.lanes() {
+        let offset = lane_layout.offset(lane);
+        let resting = Vec2::new(
+            position,
+            terrain.height_at(position) + offset + height * 0.5,
+        );
+        let mut cmd = commands.spawn();
+        entities.push(cmd.id());
+        let (body_type, pose) = if let Some(motion) = motion {
+            (
+                RigidBodyType::KinematicPositionBased,
+                add_obstacle_motion(&mut cmd, motion, resting, height),
+            )
+        } else if let Some(water_pit) = water_pit {
+            cmd.insert(WaterJump {
+                pit_length: water_pit.length,
+            });
+            (RigidBodyType::Static, resting.into())
+        } else {
+            (RigidBodyType::Dynamic, resting.into())
+        };
+        let hurdle_entity = cmd.id();
+        cmd.insert_bundle(RigidBodyBundle {
+            body_type: body_type.into(),
+            position: pose.into(),
             ..Default::default()
         });
         cmd.insert_bundle(ColliderBundle {
-            shape: ColliderShape::cuboid(HURDLE_WIDTH * 0.5, HURDLE_HEIGHT * 0.5).into(),
+            shape: ColliderShape::cuboid(width * 0.5, height * 0.5).into(),
+            flags: ColliderFlags {
+                collision_groups: lane_layout.hurdle_groups(lane),
+                solver_groups: lane_layout.hurdle_groups(lane),
+                ..Default::default()
+            }
+            .into(),
             ..Default::default()
         });
         cmd.insert_bundle(SpriteBundle {
             sprite: Sprite {
-                custom_size: Some(Vec2::new(2.0, 2.0)),
+                // The sprite is 2x2 for a standard hurdle
+                custom_size: Some(Vec2::new(
+                    2.0 * width / HURDLE_WIDTH,
+                    2.0 * height / HURDLE_HEIGHT,
+                )),
                 ..Default::default()
             },
             texture: texture_assets.hurdle.clone(),
+            transform: Transform::from_xyz(0.0, 0.0, lane_layout.z_offset(lane)),
             ..Default::default()
         });
         cmd.insert(ColliderPositionSync::Discrete);
         cmd.insert(DespawnWithLevel);
-        cmd.insert(Hurdle);
+        cmd.insert(Hurdle {
+            width,
+            height,
+            knocked_down: false,
+            passed_through: false,
+        });
+        cmd.insert(lane);
+
+        if let Some(water_pit) = water_pit {
+            entities.push(spawn_water_pit(
+                commands,
+                terrain,
+                lane_layout,
+                lane,
+                position + width * 0.5,
+                &water_pit,
+            ));
+        }
+
+        let knockable = matches!(hurdle_rules, HurdleRules::KnockDown { .. });
+        if knockable && body_type == RigidBodyType::Dynamic {
+            // Hinge the hurdle at its trailing bottom corner, so that it tips over forward when
+            // knocked instead of sliding, without its base digging into the ground
+            let base = Vec2::new(position + width * 0.5, resting.y - height * 0.5);
+            let anchor_entity = commands
+                .spawn_bundle(RigidBodyBundle {
+                    body_type: RigidBodyType::Static.into(),
+                    position: base.into(),
+                    ..Default::default()
+                })
+                .insert(DespawnWithLevel)
+                .id();
+            entities.push(anchor_entity);
+            let hinge = RevoluteJoint::new().local_anchor2(point![width * 0.5, -height * 0.5]);
+            let joint_entity = commands
+                .spawn()
+                .insert(JointBuilderComponent::new(
+                    hinge,
+                    anchor_entity,
+                    hurdle_entity,
+                ))
+                .insert(DespawnWithLevel)
+                .id();
+            entities.push(joint_entity);
+        }
+    }
+    if let Some(overhead_bar) = overhead_bar {
+        entities.extend(spawn_overhead_bar(
+            commands,
+            terrain,
+            lane_layout,
+            position,
+            &overhead_bar,
+        ));
     }
+    entities
 }
 
+/// The player's forward speed before the physics step. Contact events are only sent after the
+/// step, when the impact has already slowed the player down.
+#[derive(Default)]
+struct PlayerSpeedBeforeStep(f32);
+
+fn record_player_speed(
+    player_query: Query<&RigidBodyVelocityComponent, With<PlayerSprite>>,
+    mut speed: ResMut<PlayerSpeedBeforeStep>,
+) {
+    for velocity in player_query.iter() {
+        speed.0 = velocity.linvel.x;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn detect_hurdle_touch(
     mut reader: EventReader<ContactEvent>,
-    hurdle_query: Query<(), With<Hurdle>>,
+    mut hurdle_query: Query<&mut Hurdle>,
     player_query: Query<(), With<Player>>,
-    mut state: ResMut<State<AppState>>,
-    mut game_over_state: ResMut<State<Option<GameOver>>>,
+    player_head_query: Query<(), With<PlayerHead>>,
+    player_speed: Res<PlayerSpeedBeforeStep>,
+    current_track: Res<CurrentTrack>,
+    mut player_status: ResMut<PlayerStatus>,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+    mut fault_writer: EventWriter<PlayerFault>,
+    mut commands: Commands,
 ) {
     for event in reader.iter() {
         if let ContactEvent::Started(handle1, handle2) = event {
-            if entities_ordered_by_type!(
+            if let Some([hurdle_entity, player_entity]) = entities_ordered_by_type!(
                 [handle1.entity(), handle2.entity()],
                 hurdle_query,
                 player_query
-            )
-            .is_some()
-            {
-                state.set(AppState::Menu(MenuState::GameOver)).unwrap();
-                game_over_state.set(Some(GameOver::Disqualified)).unwrap();
+            ) {
+                let mut hurdle = hurdle_query.get_mut(hurdle_entity).unwrap();
+                if hurdle.passed_through {
+                    continue;
+                }
+                let disqualified = match current_track.0.hurdle_rules {
+                    HurdleRules::Strict => true,
+                    HurdleRules::KnockDown {
+                        penalty,
+                        disqualify_speed,
+                    } => {
+                        let head_on = player_head_query.get(player_entity).is_ok()
+                            && disqualify_speed <= player_speed.0;
+                        if hurdle.knocked_down {
+                            false
+                        } else if head_on {
+                            true
+                        } else {
+                            hurdle.knocked_down = true;
+                            player_status.hurdles_knocked += 1;
+                            player_status.penalty += Duration::from_secs_f32(penalty);
+                            false
+                        }
+                    }
+                };
+                if disqualified {
+                    // Only use up protection on contacts that would have been a fault
+                    if active_power_ups.protect_from(Hazard::Hurdle) {
+                        hurdle.passed_through = true;
+                        commands
+                            .entity(hurdle_entity)
+                            .insert(ColliderTypeComponent(ColliderType::Sensor));
+                    } else {
+                        fault_writer.send(PlayerFault(GameOver::Disqualified));
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::global_types::{AppState, Lane};
+use crate::track::{CurrentTrack, LoadCurrentTrack};
+
+pub struct LanesPlugin;
+
+impl Plugin for LanesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LaneLayout>();
+        app.add_system_set(
+            SystemSet::on_enter(AppState::ClearLevelAndThenLoad)
+                .with_system(update_lane_layout.after(LoadCurrentTrack)),
+        );
+    }
+}
+
+/// Collision groups are split so that bits 0-14 are the lanes' grounds, bits 16-30 are the lanes'
+/// hurdles and bit 31 marks the parts of the player's ragdoll.
+const MAX_LANES: usize = 15;
+const HURDLE_GROUPS_SHIFT: usize = 16;
+const RAGDOLL_GROUP: u32 = 1 << 31;
+
+/// Where the lanes are and how they are kept apart. Without lanes there is a single lane 0 where
+/// everything collides with everything.
+#[derive(Default)]
+pub struct LaneLayout {
+    enabled: bool,
+    count: usize,
+    spacing: f32,
+    player_lane: usize,
+}
+
+impl LaneLayout {
+    pub fn lanes(&self) -> impl Iterator<Item = Lane> {
+        (0..self.count.max(1)).map(Lane)
+    }
+
+    pub fn player_lane(&self) -> Lane {
+        Lane(self.player_lane)
+    }
+
+    /// Competitors that don't fit in the lanes share the last one.
+    pub fn competitor_lane(&self, competitor_index: usize) -> Lane {
+        if !self.enabled {
+            Lane(0)
+        } else if competitor_index < self.player_lane {
+            Lane(competitor_index)
+        } else {
+            Lane((competitor_index + 1).min(self.count - 1))
+        }
+    }
+
+    /// How much higher than the terrain the lane's ground is.
+    pub fn offset(&self, lane: Lane) -> f32 {
+        self.spacing * lane.0 as f32
+    }
+
+    /// Higher lanes are further away, so they are drawn behind the lower ones.
+    pub fn z_offset(&self, lane: Lane) -> f32 {
+        -(lane.0 as f32)
+    }
+
+    fn lane_bits(&self, lane: Lane) -> u32 {
+        (1 << lane.0) | (1 << (lane.0 + HURDLE_GROUPS_SHIFT))
+    }
+
+    /// For the lane's ground and everything attached to it, like water pits.
+    pub fn ground_groups(&self, lane: Lane) -> InteractionGroups {
+        if self.enabled {
+            InteractionGroups::new(1 << lane.0, self.lane_bits(lane) | RAGDOLL_GROUP)
+        } else {
+            InteractionGroups::all()
+        }
+    }
+
+    pub fn hurdle_groups(&self, lane: Lane) -> InteractionGroups {
+        if self.enabled {
+            InteractionGroups::new(1 << (lane.0 + HURDLE_GROUPS_SHIFT), self.lane_bits(lane))
+        } else {
+            InteractionGroups::all()
+        }
+    }
+
+    /// For runners - they only touch their own lane's ground and hurdles.
+    pub fn runner_groups(&self, lane: Lane) -> InteractionGroups {
+        if self.enabled {
+            InteractionGroups::new(self.lane_bits(lane), self.lane_bits(lane))
+        } else {
+            InteractionGroups::all()
+        }
+    }
+
+    /// For the parts of the player's ragdoll after a crash - they touch the ground, but not each
+    /// other, so that the joint holds them together without the colliders fighting it.
+    pub fn ragdoll_groups(&self, lane: Lane) -> InteractionGroups {
+        let ground = self.ground_groups(lane);
+        InteractionGroups::new(RAGDOLL_GROUP, ground.memberships & !RAGDOLL_GROUP)
+    }
+}
+
+fn update_lane_layout(current_track: Res<CurrentTrack>, mut lane_layout: ResMut<LaneLayout>) {
+    let track = &current_track.0;
+    *lane_layout = if let Some(lanes) = &track.lanes {
+        let count = (track.competitors.len() + 1).min(MAX_LANES);
+        LaneLayout {
+            enabled: true,
+            count,
+            spacing: lanes.spacing,
+            player_lane: lanes.player_lane.min(count - 1),
+        }
+    } else {
+        LaneLayout::default()
+    };
+}
@@ -1,13 +1,29 @@
 mod camera;
+pub mod checkpoints;
 mod competitors;
-mod ground;
-mod hurdles;
+mod crash;
+pub mod endless;
+pub mod ground;
+pub mod hurdle_profile;
+pub mod hurdles;
+pub mod lanes;
+mod obstacles;
+mod overhead_bars;
+pub mod pickups;
 mod pogo;
+pub mod power_ups;
+pub mod pump;
+pub mod tricks;
+mod water;
+pub mod weather;
+pub mod wind;
 
 use bevy::ecs::schedule::ShouldRun;
 use bevy::prelude::*;
 
-use crate::global_types::{AppState, DespawnWithLevel, GameOver, MenuState, PlayerStatus};
+use crate::global_types::{
+    AppState, AssistLevel, DespawnWithLevel, GameOver, MenuState, PlayerStatus, Rider,
+};
 
 pub struct GameSystemsPlugin;
 
@@ -30,10 +46,23 @@ impl Plugin for GameSystemsPlugin {
             SystemSet::on_enter(AppState::LoadLevel)
                 .with_system(create_move_to_state_system(AppState::Game)),
         );
+        app.add_plugin(lanes::LanesPlugin);
         app.add_plugin(ground::GroundPlugin);
         app.add_plugin(pogo::PogoPlugin);
         app.add_plugin(hurdles::HurdlesPlugin);
+        app.add_plugin(obstacles::ObstaclesPlugin);
+        app.add_plugin(water::WaterPlugin);
+        app.add_plugin(overhead_bars::OverheadBarsPlugin);
+        app.add_plugin(pickups::PickupsPlugin);
+        app.add_plugin(power_ups::PowerUpsPlugin);
+        app.add_plugin(pump::PumpPlugin);
+        app.add_plugin(tricks::TricksPlugin);
         app.add_plugin(competitors::CompetitorsPlugin);
+        app.add_plugin(checkpoints::CheckpointsPlugin);
+        app.add_plugin(crash::CrashPlugin);
+        app.add_plugin(endless::EndlessPlugin);
+        app.add_plugin(wind::WindPlugin);
+        app.add_plugin(weather::WeatherPlugin);
         app.add_system(enable_disable_physics.with_run_criteria(run_on_state_change));
     }
 }
@@ -43,11 +72,11 @@ pub fn run_on_state_change(
     mut prev_state: Local<Option<AppState>>,
 ) -> ShouldRun {
     let state = state.current();
-    if Some(state) == (&*prev_state).as_ref() {
+    if Some(state) == prev_state.as_ref() {
         return ShouldRun::No;
     }
     *prev_state = Some(state.clone());
-    return ShouldRun::Yes;
+    ShouldRun::Yes
 }
 
 fn clear_level(mut commands: Commands, query: Query<Entity, With<DespawnWithLevel>>) {
@@ -59,9 +88,15 @@ fn clear_level(mut commands: Commands, query: Query<Entity, With<DespawnWithLeve
 fn reset_resources(
     mut game_over_state: ResMut<State<Option<GameOver>>>,
     mut player_status: ResMut<PlayerStatus>,
+    assist_level: Res<AssistLevel>,
+    rider: Res<Rider>,
 ) {
     let _ = game_over_state.set(None);
-    *player_status = PlayerStatus::default();
+    *player_status = PlayerStatus {
+        assist_level: *assist_level,
+        rider: *rider,
+        ..Default::default()
+    };
 }
 
 fn enable_disable_physics(
@@ -70,8 +105,12 @@ fn enable_disable_physics(
 ) {
     let set_to = match state.current() {
         AppState::Game => true,
+        AppState::Crash => true,
         AppState::Menu(MenuState::GameOver) => true,
-        AppState::Menu(_) | AppState::ClearLevelAndThenLoad | AppState::LoadLevel => false,
+        AppState::Menu(_)
+        | AppState::ClearLevelAndThenLoad
+        | AppState::LoadLevel
+        | AppState::Editor => false,
     };
     rapier_configuration.physics_pipeline_active = set_to;
     rapier_configuration.query_pipeline_active = set_to;
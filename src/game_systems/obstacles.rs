@@ -0,0 +1,164 @@
+use std::f32::consts::TAU;
+
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::global_types::{AppState, PlayerStatus};
+use crate::track::ObstacleMotion;
+
+pub struct ObstaclesPlugin;
+
+impl Plugin for ObstaclesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set({
+            SystemSet::on_update(AppState::Game)
+                .with_system(move_obstacles::<SlidingObstacle>)
+                .with_system(move_obstacles::<SwingingObstacle>)
+                .with_system(move_obstacles::<RisingObstacle>)
+        });
+    }
+}
+
+/// A kinematic obstacle whose pose is a function of the race time.
+trait ObstaclePose {
+    fn pose_at(&self, time: f32) -> Isometry<f32>;
+}
+
+#[derive(Component)]
+struct SlidingObstacle {
+    resting: Vec2,
+    amplitude: f32,
+    period: f32,
+    phase: f32,
+}
+
+impl ObstaclePose for SlidingObstacle {
+    fn pose_at(&self, time: f32) -> Isometry<f32> {
+        let cycle = ((time + self.phase) / self.period * TAU).cos();
+        let offset = 0.5 * self.amplitude * (1.0 - cycle);
+        (self.resting + Vec2::new(0.0, offset)).into()
+    }
+}
+
+#[derive(Component)]
+struct SwingingObstacle {
+    pivot: Vec2,
+    length: f32,
+    amplitude: f32,
+    period: f32,
+    phase: f32,
+}
+
+impl ObstaclePose for SwingingObstacle {
+    fn pose_at(&self, time: f32) -> Isometry<f32> {
+        let angle = self.amplitude * ((time + self.phase) / self.period * TAU).sin();
+        let center = self.pivot + Vec2::new(angle.sin(), -angle.cos()) * (0.5 * self.length);
+        Isometry::new(center.into(), angle)
+    }
+}
+
+#[derive(Component)]
+struct RisingObstacle {
+    resting: Vec2,
+    height: f32,
+    down_time: f32,
+    up_time: f32,
+    transition_time: f32,
+    phase: f32,
+}
+
+impl ObstaclePose for RisingObstacle {
+    fn pose_at(&self, time: f32) -> Isometry<f32> {
+        let cycle = self.down_time + self.up_time + 2.0 * self.transition_time;
+        let mut time_in_cycle = (time + self.phase).rem_euclid(cycle);
+        let mut progress = || {
+            for (duration, from, to) in [
+                (self.down_time, 0.0, 0.0),
+                (self.transition_time, 0.0, 1.0),
+                (self.up_time, 1.0, 1.0),
+                (self.transition_time, 1.0, 0.0),
+            ] {
+                if time_in_cycle < duration {
+                    return from + (to - from) * time_in_cycle / duration;
+                }
+                time_in_cycle -= duration;
+            }
+            0.0
+        };
+        (self.resting + Vec2::new(0.0, self.height * progress())).into()
+    }
+}
+
+/// Make a hurdle move according to `motion`, and return its initial pose.
+///
+/// `resting` is the position of a static hurdle of the same dimensions at the same place.
+pub fn add_obstacle_motion(
+    cmd: &mut EntityCommands,
+    motion: ObstacleMotion,
+    resting: Vec2,
+    height: f32,
+) -> Isometry<f32> {
+    fn insert<T: Component + ObstaclePose>(cmd: &mut EntityCommands, obstacle: T) -> Isometry<f32> {
+        let pose = obstacle.pose_at(0.0);
+        cmd.insert(obstacle);
+        pose
+    }
+    match motion {
+        ObstacleMotion::Slide {
+            amplitude,
+            period,
+            phase,
+        } => insert(
+            cmd,
+            SlidingObstacle {
+                resting,
+                amplitude,
+                period,
+                phase,
+            },
+        ),
+        ObstacleMotion::Swing {
+            pivot_height,
+            amplitude,
+            period,
+            phase,
+        } => insert(
+            cmd,
+            SwingingObstacle {
+                pivot: resting + Vec2::new(0.0, pivot_height - 0.5 * height),
+                length: height,
+                amplitude,
+                period,
+                phase,
+            },
+        ),
+        ObstacleMotion::RiseAndFall {
+            height: rise_height,
+            down_time,
+            up_time,
+            transition_time,
+            phase,
+        } => insert(
+            cmd,
+            RisingObstacle {
+                resting,
+                height: rise_height,
+                down_time,
+                up_time,
+                transition_time,
+                phase,
+            },
+        ),
+    }
+}
+
+fn move_obstacles<T: Component + ObstaclePose>(
+    player_status: Res<PlayerStatus>,
+    mut query: Query<(&T, &mut RigidBodyPositionComponent)>,
+) {
+    let time = player_status.time.as_secs_f32();
+    for (obstacle, mut position) in query.iter_mut() {
+        position.next_position = obstacle.pose_at(time);
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::game_systems::checkpoints::PlayerFault;
+use crate::game_systems::lanes::LaneLayout;
+use crate::global_types::{AppState, DespawnWithLevel, GameOver, OverheadBar, PlayerHead};
+use crate::track::{OverheadBarDefinition, Terrain};
+use crate::utils::entities_ordered_by_type;
+
+pub struct OverheadBarsPlugin;
+
+impl Plugin for OverheadBarsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(detect_overhead_bar_touch),
+        );
+    }
+}
+
+const BAR_THICKNESS: f32 = 0.4;
+const POST_WIDTH: f32 = 0.1;
+
+/// Bars only hang over the player's lane - the competitors' jumps are scripted and can't duck
+/// under them. The bar is a sensor, since touching it ends the run anyway, and its posts are only
+/// drawn.
+pub fn spawn_overhead_bar(
+    commands: &mut Commands,
+    terrain: &Terrain,
+    lane_layout: &LaneLayout,
+    hurdle_position: f32,
+    definition: &OverheadBarDefinition,
+) -> Vec<Entity> {
+    let lane = lane_layout.player_lane();
+    let x = hurdle_position + definition.offset;
+    let ground = terrain.height_at(x) + lane_layout.offset(lane);
+    let center = Vec2::new(x, ground + definition.clearance + BAR_THICKNESS * 0.5);
+    let z = 0.3 + lane_layout.z_offset(lane);
+    let mut entities = Vec::new();
+
+    let mut cmd = commands.spawn_bundle(ColliderBundle {
+        collider_type: ColliderType::Sensor.into(),
+        shape: ColliderShape::cuboid(definition.length * 0.5, BAR_THICKNESS * 0.5).into(),
+        position: center.into(),
+        flags: ColliderFlags {
+            active_events: ActiveEvents::INTERSECTION_EVENTS,
+            collision_groups: lane_layout.ground_groups(lane),
+            ..Default::default()
+        }
+        .into(),
+        ..Default::default()
+    });
+    cmd.insert_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgb(0.85, 0.2, 0.2),
+            custom_size: Some(Vec2::new(definition.length, BAR_THICKNESS)),
+            ..Default::default()
+        },
+        transform: Transform::from_xyz(center.x, center.y, z),
+        ..Default::default()
+    });
+    cmd.insert(DespawnWithLevel);
+    cmd.insert(OverheadBar);
+    entities.push(cmd.id());
+
+    for post_x in [
+        x - 0.5 * (definition.length + POST_WIDTH),
+        x + 0.5 * (definition.length + POST_WIDTH),
+    ] {
+        let post_bottom = terrain.height_at(post_x) + lane_layout.offset(lane);
+        let post_top = center.y + BAR_THICKNESS * 0.5;
+        let post_entity = commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.5, 0.5, 0.55),
+                    custom_size: Some(Vec2::new(POST_WIDTH, post_top - post_bottom)),
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(post_x, 0.5 * (post_bottom + post_top), z),
+                ..Default::default()
+            })
+            .insert(DespawnWithLevel)
+            .id();
+        entities.push(post_entity);
+    }
+    entities
+}
+
+fn detect_overhead_bar_touch(
+    mut reader: EventReader<IntersectionEvent>,
+    bar_query: Query<(), With<OverheadBar>>,
+    player_head_query: Query<(), With<PlayerHead>>,
+    mut fault_writer: EventWriter<PlayerFault>,
+) {
+    for event in reader.iter() {
+        if !event.intersecting {
+            continue;
+        }
+        if entities_ordered_by_type!(
+            [event.collider1.entity(), event.collider2.entity()],
+            bar_query,
+            player_head_query
+        )
+        .is_some()
+        {
+            fault_writer.send(PlayerFault(GameOver::Injured));
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::prelude::SliceRandom;
+
+use crate::game_systems::hurdles::{distribute_distances, hurdle_definitions};
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::power_ups::ActivePowerUps;
+use crate::global_types::{AppState, DespawnWithLevel, Player, PlayerStatus, RngStream, TrackSeed};
+use crate::track::{
+    CurrentTrack, PickupDefinition, PickupKind, PickupLayout, Terrain, TrackDefinition,
+};
+use crate::utils::{entities_ordered_by_type, random_between};
+
+pub struct PickupsPlugin;
+
+impl Plugin for PickupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::LoadLevel).with_system(add_pickups));
+        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(detect_pickup_touch));
+    }
+}
+
+#[derive(Component)]
+struct Pickup {
+    kind: PickupKind,
+    collected: bool,
+}
+
+const PICKUP_SIZE: f32 = 0.5;
+
+/// How far above a hurdle (or below an overhead bar) generated pickups are moved.
+const PICKUP_CLEARANCE: f32 = 0.5;
+
+/// The pickups of the track. Generated layouts are always the same for the same track seed, and
+/// are only spread over the track bounds.
+pub fn pickup_definitions(
+    track: &TrackDefinition,
+    track_seed: &TrackSeed,
+) -> Vec<PickupDefinition> {
+    match &track.pickups {
+        PickupLayout::Explicit(pickups) => pickups.clone(),
+        PickupLayout::Generated {
+            before_first,
+            spacing,
+            min_height,
+            max_height,
+            mix,
+        } => {
+            let allowed_width = track.bounds.right - before_first;
+            let placements = distribute_distances(
+                &mut track_seed.rng(RngStream::PickupPlacement),
+                (allowed_width / spacing) as usize,
+                *before_first..track.bounds.right,
+                *spacing,
+            );
+            let hurdles = hurdle_definitions(track, track_seed);
+            let mut rng = track_seed.rng(RngStream::PickupTypes);
+            placements
+                .into_iter()
+                .filter_map(|position| {
+                    let entry = mix.choose_weighted(&mut rng, |entry| entry.weight).ok()?;
+                    let mut height = random_between(&mut rng, *min_height, *max_height);
+                    for hurdle in hurdles.iter() {
+                        let half_width = 0.5 * (hurdle.dimensions.width + PICKUP_SIZE);
+                        if (position - hurdle.position).abs() < half_width {
+                            height = height.max(hurdle.dimensions.height + PICKUP_CLEARANCE);
+                        }
+                        if let Some(overhead_bar) = hurdle.overhead_bar {
+                            let bar_position = hurdle.position + overhead_bar.offset;
+                            let half_length = 0.5 * (overhead_bar.length + PICKUP_SIZE);
+                            if (position - bar_position).abs() < half_length {
+                                height = height.min(overhead_bar.clearance - PICKUP_CLEARANCE);
+                            }
+                        }
+                    }
+                    Some(PickupDefinition {
+                        position,
+                        height,
+                        kind: entry.kind,
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+fn pickup_color(kind: PickupKind) -> Color {
+    match kind {
+        PickupKind::Coin => Color::rgb(1.0, 0.85, 0.1),
+        PickupKind::TimeBonus { .. } => Color::rgb(0.2, 0.9, 0.9),
+        PickupKind::PowerUp(_) => Color::rgb(0.8, 0.3, 0.9),
+    }
+}
+
+/// Pickups are only placed in the player's lane, since competitors don't collect them.
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    terrain: &Terrain,
+    lane_layout: &LaneLayout,
+    definition: &PickupDefinition,
+) -> Entity {
+    let lane = lane_layout.player_lane();
+    let center = Vec2::new(
+        definition.position,
+        terrain.height_at(definition.position) + lane_layout.offset(lane) + definition.height,
+    );
+    let mut cmd = commands.spawn_bundle(ColliderBundle {
+        collider_type: ColliderType::Sensor.into(),
+        shape: ColliderShape::ball(PICKUP_SIZE * 0.5).into(),
+        position: center.into(),
+        flags: ColliderFlags {
+            active_events: ActiveEvents::INTERSECTION_EVENTS,
+            collision_groups: lane_layout.ground_groups(lane),
+            ..Default::default()
+        }
+        .into(),
+        ..Default::default()
+    });
+    cmd.insert_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: pickup_color(definition.kind),
+            custom_size: Some(Vec2::splat(PICKUP_SIZE / 2f32.sqrt())),
+            ..Default::default()
+        },
+        transform: Transform {
+            translation: center.extend(0.3 + lane_layout.z_offset(lane)),
+            rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    cmd.insert(DespawnWithLevel);
+    cmd.insert(Pickup {
+        kind: definition.kind,
+        collected: false,
+    });
+    cmd.id()
+}
+
+fn add_pickups(
+    mut commands: Commands,
+    current_track: Res<CurrentTrack>,
+    track_seed: Res<TrackSeed>,
+    terrain: Res<Terrain>,
+    lane_layout: Res<LaneLayout>,
+) {
+    for pickup in pickup_definitions(&current_track.0, &track_seed) {
+        spawn_pickup(&mut commands, &terrain, &lane_layout, &pickup);
+    }
+}
+
+fn detect_pickup_touch(
+    mut reader: EventReader<IntersectionEvent>,
+    mut pickup_query: Query<&mut Pickup>,
+    player_query: Query<(), With<Player>>,
+    mut player_status: ResMut<PlayerStatus>,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+    mut commands: Commands,
+) {
+    for event in reader.iter() {
+        if !event.intersecting {
+            continue;
+        }
+        if let Some([pickup_entity, _]) = entities_ordered_by_type!(
+            [event.collider1.entity(), event.collider2.entity()],
+            pickup_query,
+            player_query
+        ) {
+            let mut pickup = pickup_query.get_mut(pickup_entity).unwrap();
+            // Both the body and the stick can touch the pickup at once
+            if pickup.collected {
+                continue;
+            }
+            pickup.collected = true;
+            match pickup.kind {
+                PickupKind::Coin => {
+                    player_status.score += 1;
+                }
+                PickupKind::TimeBonus { seconds } => {
+                    player_status.score += 1;
+                    player_status.time_bonus += Duration::from_secs_f32(seconds);
+                }
+                PickupKind::PowerUp(kind) => {
+                    active_power_ups.activate(kind, player_status.time);
+                }
+            }
+            commands.entity(pickup_entity).despawn_recursive();
+        }
+    }
+}
@@ -2,64 +2,215 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use ezinput::prelude::*;
 
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::power_ups::ActivePowerUps;
+use crate::game_systems::weather::Weather;
 use crate::global_types::{
-    AppState, CameraFollowTarget, DespawnWithLevel, GameBoundaries, GameOver, MenuState, Player,
-    PlayerHead, PlayerSprite, PlayerStatus, InputBinding,
+    AppState, AssistLevel, CameraFollowTarget, DespawnWithLevel, GameBoundaries, GameOver,
+    InputBinding, MenuState, Player, PlayerHead, PlayerSprite, PlayerStatus, Rider,
 };
-use crate::loading::TextureAssets;
+use crate::loading::AnimationAssets;
+use crate::track::Terrain;
+use crate::tuning::PogoTuning;
 
 pub struct PogoPlugin;
 
 impl Plugin for PogoPlugin {
     fn build(&self, app: &mut App) {
+        app.add_event::<RespawnPlayer>();
         app.add_system_set(SystemSet::on_enter(AppState::LoadLevel).with_system(spawn_player));
         app.add_system_set({
             SystemSet::on_update(AppState::Game)
                 .with_system(player_controls)
+                .with_system(respawn_player)
                 .with_system(automatically_balance_player)
                 .with_system(detect_out_of_bounds)
                 .with_system(update_player_status)
+                .with_system(update_stick_material)
+                // Only while the rider and the stick are one body - not after a crash splits them
+                .with_system(apply_pogo_tuning)
         });
     }
 }
 
+/// Pushes the player back upright. Not added at all when playing without assist.
 #[derive(Component)]
-struct AutoBalance;
+struct AutoBalance {
+    torque_scale: f32,
+    /// Per second.
+    angular_velocity_damping: f32,
+}
+
+impl AutoBalance {
+    fn for_assist_level(assist_level: AssistLevel) -> Option<Self> {
+        let (torque_scale, angular_velocity_damping) = match assist_level {
+            AssistLevel::Off => return None,
+            AssistLevel::Light => (0.5, 0.0),
+            AssistLevel::Standard => (1.0, 0.0),
+            AssistLevel::Strong => (1.0, 2.0),
+        };
+        Some(Self {
+            torque_scale,
+            angular_velocity_damping,
+        })
+    }
+}
+
+/// The material of the stick when nothing affects it.
+#[derive(Component)]
+pub struct PogoStick {
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+/// Replaces the stick's material while it is in water.
+#[derive(Component)]
+pub struct StickInWater {
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+/// Drop the player back onto the track at `x`, standing still and upright.
+pub struct RespawnPlayer {
+    pub x: f32,
+}
+
+/// Where the player is dropped from when placed on the track at `x`.
+fn drop_position(terrain: &Terrain, lane_layout: &LaneLayout, x: f32) -> Vec2 {
+    let lane = lane_layout.player_lane();
+    Vec2::new(x, terrain.height_at(x) + lane_layout.offset(lane) + 4.0)
+}
+
+/// How a rider differs from the pogo tuning.
+pub struct RiderStats {
+    mass: f32,
+    inertia_scale: f32,
+    /// Scales the height of the center of mass. Lower is more stable.
+    center_of_mass_scale: f32,
+    stick_restitution_scale: f32,
+    /// Scales how long it takes to fully charge the pump.
+    pub pump_charge_time_scale: f32,
+}
+
+pub fn rider_stats(rider: Rider) -> RiderStats {
+    match rider {
+        Rider::Classic => RiderStats {
+            mass: 1.0,
+            inertia_scale: 1.0,
+            center_of_mass_scale: 1.0,
+            stick_restitution_scale: 1.0,
+            pump_charge_time_scale: 1.0,
+        },
+        Rider::Heavy => RiderStats {
+            mass: 1.5,
+            inertia_scale: 1.6,
+            center_of_mass_scale: 0.8,
+            stick_restitution_scale: 0.95,
+            pump_charge_time_scale: 1.4,
+        },
+        Rider::Light => RiderStats {
+            mass: 0.7,
+            inertia_scale: 0.6,
+            center_of_mass_scale: 1.1,
+            stick_restitution_scale: 1.0,
+            pump_charge_time_scale: 1.0,
+        },
+        Rider::Bouncy => RiderStats {
+            mass: 1.0,
+            inertia_scale: 1.0,
+            center_of_mass_scale: 1.0,
+            stick_restitution_scale: 1.15,
+            pump_charge_time_scale: 1.0,
+        },
+    }
+}
+
+fn player_mass_properties(pogo_tuning: &PogoTuning, rider_stats: &RiderStats) -> MassProperties {
+    let (x, y) = pogo_tuning.center_of_mass;
+    let inertia = pogo_tuning.inertia * rider_stats.inertia_scale;
+    MassProperties {
+        local_com: point![x, y * rider_stats.center_of_mass_scale],
+        inv_mass: rider_stats.mass.recip(),
+        inv_principal_inertia_sqrt: inertia.sqrt().recip(),
+    }
+}
+
+fn stick_restitution(pogo_tuning: &PogoTuning, rider_stats: &RiderStats) -> f32 {
+    pogo_tuning.stick_restitution * rider_stats.stick_restitution_scale
+}
+
+pub const RIDER_SPRITE_INDEX: usize = 0;
+pub const STICK_SPRITE_INDEX: usize = 1;
 
-fn spawn_player(mut commands: Commands, texture_assets: Res<TextureAssets>) {
+/// The part of the player sprite that shows the stick. Replaced by a separate body when crashing.
+#[derive(Component)]
+pub struct StickSprite;
+
+fn spawn_player(
+    mut commands: Commands,
+    animation_assets: Res<AnimationAssets>,
+    terrain: Res<Terrain>,
+    lane_layout: Res<LaneLayout>,
+    pogo_tuning: Res<PogoTuning>,
+    player_status: Res<PlayerStatus>,
+) {
+    let lane = lane_layout.player_lane();
+    let rider_stats = rider_stats(player_status.rider);
+    let flags = ColliderFlags {
+        active_events: ActiveEvents::CONTACT_EVENTS,
+        collision_groups: lane_layout.runner_groups(lane),
+        solver_groups: lane_layout.runner_groups(lane),
+        ..Default::default()
+    };
     let mut player_cmd = commands.spawn_bundle(RigidBodyBundle {
         body_type: RigidBodyType::Dynamic.into(),
-        position: point![0.0, 4.0].into(),
-        mass_properties: MassProperties {
-            local_com: point![0.0, 1.0],
-            inv_mass: 1.0,
-            inv_principal_inertia_sqrt: 1.0,
-        }
-        .into(),
+        position: drop_position(&terrain, &lane_layout, 0.0).into(),
+        mass_properties: player_mass_properties(&pogo_tuning, &rider_stats).into(),
         damping: RigidBodyDamping {
             linear_damping: 0.0,
-            angular_damping: 1.0,
+            angular_damping: pogo_tuning.angular_damping,
         }
         .into(),
         ..Default::default()
     });
-    player_cmd.insert_bundle(SpriteBundle {
-        sprite: Sprite {
-            custom_size: Some(Vec2::new(2.0, 2.0)),
-            ..Default::default()
-        },
-        transform: Transform {
-            translation: Vec3::new(0.0, 0.0, 0.1),
+    player_cmd.insert(Transform::from_xyz(
+        0.0,
+        0.0,
+        0.1 + lane_layout.z_offset(lane),
+    ));
+    player_cmd.insert(GlobalTransform::default());
+    player_cmd.with_children(|commands| {
+        commands.spawn_bundle(SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                index: RIDER_SPRITE_INDEX,
+                custom_size: Some(Vec2::new(2.0, 1.5)),
+                ..Default::default()
+            },
+            texture_atlas: animation_assets.player_atlas(player_status.rider),
+            transform: Transform::from_xyz(0.0, 0.25, 0.0),
             ..Default::default()
-        },
-        texture: texture_assets.pogo_player.clone(),
-        ..Default::default()
+        });
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: STICK_SPRITE_INDEX,
+                    custom_size: Some(Vec2::new(2.0, 0.5)),
+                    ..Default::default()
+                },
+                texture_atlas: animation_assets.player_atlas(player_status.rider),
+                transform: Transform::from_xyz(0.0, -0.75, 0.0),
+                ..Default::default()
+            })
+            .insert(StickSprite);
     });
     player_cmd.insert(DespawnWithLevel);
-    player_cmd.insert(AutoBalance);
+    if let Some(auto_balance) = AutoBalance::for_assist_level(player_status.assist_level) {
+        player_cmd.insert(auto_balance);
+    }
     player_cmd.insert(RigidBodyPositionSync::Discrete);
     player_cmd.insert(CameraFollowTarget);
     player_cmd.insert(PlayerSprite);
+    player_cmd.insert(lane);
 
     let player_entity = player_cmd.id();
 
@@ -70,16 +221,13 @@ fn spawn_player(mut commands: Commands, texture_assets: Res<TextureAssets>) {
     }));
     body_cmd.insert_bundle(ColliderBundle {
         shape: ColliderShape::cuboid(0.5, 0.75).into(),
-        flags: ColliderFlags {
-            active_events: ActiveEvents::CONTACT_EVENTS,
-            ..Default::default()
-        }
-        .into(),
+        flags: flags.into(),
         position: Vec2::new(0.0, 1.0).into(),
         ..Default::default()
     });
     body_cmd.insert(Player);
     body_cmd.insert(PlayerHead);
+    body_cmd.insert(lane);
 
     let mut stick_cmd = commands.spawn();
     stick_cmd.insert(ColliderParentComponent(ColliderParent {
@@ -88,13 +236,9 @@ fn spawn_player(mut commands: Commands, texture_assets: Res<TextureAssets>) {
     }));
     stick_cmd.insert_bundle(ColliderBundle {
         shape: ColliderShape::cuboid(0.1, 0.25).into(),
-        flags: ColliderFlags {
-            active_events: ActiveEvents::CONTACT_EVENTS,
-            ..Default::default()
-        }
-        .into(),
+        flags: flags.into(),
         material: ColliderMaterial {
-            restitution: 2.0,
+            restitution: stick_restitution(&pogo_tuning, &rider_stats),
             friction: 1.0,
             ..Default::default()
         }
@@ -103,6 +247,11 @@ fn spawn_player(mut commands: Commands, texture_assets: Res<TextureAssets>) {
     });
     stick_cmd.insert(DespawnWithLevel);
     stick_cmd.insert(Player);
+    stick_cmd.insert(lane);
+    stick_cmd.insert(PogoStick {
+        restitution: stick_restitution(&pogo_tuning, &rider_stats),
+        friction: 1.0,
+    });
 }
 
 fn player_controls(
@@ -115,6 +264,8 @@ fn player_controls(
         ),
         With<PlayerSprite>,
     >,
+    pogo_tuning: Res<PogoTuning>,
+    active_power_ups: Res<ActivePowerUps>,
 ) {
     let mut spin_value = 0.0;
     let mut num_participating = 0;
@@ -128,30 +279,57 @@ fn player_controls(
     }
     if 0 < num_participating {
         spin_value /= num_participating as f32;
-        let torque = time.delta().as_secs_f32() * 30.0 * spin_value;
+        // Slow-mo slows down the physics, so the controls have to slow down with it
+        let delta = time.delta_seconds() * active_power_ups.time_scale();
+        let torque = delta * pogo_tuning.control_torque * spin_value;
         for (mut velocity, mass_props) in query.iter_mut() {
             velocity.apply_torque_impulse(mass_props, torque);
         }
     }
 }
 
-fn automatically_balance_player(
-    time: Res<Time>,
+fn respawn_player(
+    mut reader: EventReader<RespawnPlayer>,
     mut query: Query<
         (
-            &RigidBodyPositionComponent,
+            &mut RigidBodyPositionComponent,
             &mut RigidBodyVelocityComponent,
-            &RigidBodyMassPropsComponent,
         ),
-        With<AutoBalance>,
+        With<PlayerSprite>,
     >,
+    terrain: Res<Terrain>,
+    lane_layout: Res<LaneLayout>,
+) {
+    if let Some(RespawnPlayer { x }) = reader.iter().last() {
+        let isometry: Isometry<f32> = drop_position(&terrain, &lane_layout, *x).into();
+        for (mut position, mut velocity) in query.iter_mut() {
+            position.position = isometry;
+            position.next_position = isometry;
+            velocity.linvel = Vector::zeros();
+            velocity.angvel = 0.0;
+        }
+    }
+}
+
+fn automatically_balance_player(
+    time: Res<Time>,
+    mut query: Query<(
+        &RigidBodyPositionComponent,
+        &mut RigidBodyVelocityComponent,
+        &RigidBodyMassPropsComponent,
+        &AutoBalance,
+    )>,
+    pogo_tuning: Res<PogoTuning>,
+    active_power_ups: Res<ActivePowerUps>,
 ) {
-    let torque = time.delta().as_secs_f32() * 20.0;
-    for (position, mut velocity, mass_props) in query.iter_mut() {
+    let delta = time.delta_seconds() * active_power_ups.time_scale();
+    for (position, mut velocity, mass_props, auto_balance) in query.iter_mut() {
         let angle = position.0.position.rotation.angle();
-        if 0.2 <= angle.abs() {
+        if pogo_tuning.auto_balance_threshold <= angle.abs() {
+            let torque = delta * pogo_tuning.auto_balance_torque * auto_balance.torque_scale;
             velocity.apply_torque_impulse(mass_props, torque * -angle.clamp(-1.0, 1.0));
         }
+        velocity.angvel *= 1.0 - (delta * auto_balance.angular_velocity_damping).min(1.0);
     }
 }
 
@@ -177,9 +355,65 @@ fn update_player_status(
     time: Res<Time>,
     player_query: Query<&GlobalTransform, With<PlayerSprite>>,
     mut player_status: ResMut<PlayerStatus>,
+    active_power_ups: Res<ActivePowerUps>,
 ) {
     for player_position in player_query.iter() {
         player_status.distance_traveled = player_position.translation.x;
     }
-    player_status.time += time.delta();
+    // Race time follows the physics, so slowing it down doesn't cost time
+    player_status.time += time.delta().mul_f32(active_power_ups.time_scale());
+}
+
+fn update_stick_material(
+    mut query: Query<(
+        &PogoStick,
+        Option<&StickInWater>,
+        &mut ColliderMaterialComponent,
+    )>,
+    active_power_ups: Res<ActivePowerUps>,
+    weather: Res<Weather>,
+) {
+    for (stick, in_water, mut material) in query.iter_mut() {
+        let (restitution, friction) = if let Some(in_water) = in_water {
+            (in_water.restitution, in_water.friction)
+        } else {
+            (stick.restitution, stick.friction)
+        };
+        let restitution = restitution
+            * active_power_ups.stick_restitution_scale()
+            * weather.stick_restitution_scale();
+        let friction = friction * weather.stick_friction_scale();
+        if material.restitution != restitution || material.friction != friction {
+            material.restitution = restitution;
+            material.friction = friction;
+        }
+    }
+}
+
+/// Retune the player that is already on the track when the tuning is reloaded.
+fn apply_pogo_tuning(
+    pogo_tuning: Res<PogoTuning>,
+    mut body_query: Query<
+        (
+            &RigidBodyPositionComponent,
+            &mut RigidBodyMassPropsComponent,
+            &mut RigidBodyDampingComponent,
+        ),
+        With<PlayerSprite>,
+    >,
+    mut stick_query: Query<&mut PogoStick>,
+    player_status: Res<PlayerStatus>,
+) {
+    if !pogo_tuning.is_changed() {
+        return;
+    }
+    let rider_stats = rider_stats(player_status.rider);
+    for (position, mut mass_props, mut damping) in body_query.iter_mut() {
+        mass_props.local_mprops = player_mass_properties(&pogo_tuning, &rider_stats);
+        mass_props.update_world_mass_properties(&position.position);
+        damping.angular_damping = pogo_tuning.angular_damping;
+    }
+    for mut stick in stick_query.iter_mut() {
+        stick.restitution = stick_restitution(&pogo_tuning, &rider_stats);
+    }
 }
@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::global_types::{AppState, Hurdle, PlayerStatus};
+use crate::track::PowerUpKind;
+
+pub struct PowerUpsPlugin;
+
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActivePowerUps>();
+        app.add_system_set(
+            SystemSet::on_enter(AppState::ClearLevelAndThenLoad).with_system(reset_power_ups),
+        );
+        app.add_system_set({
+            SystemSet::on_update(AppState::Game)
+                .with_system(expire_power_ups)
+                .with_system(fade_passed_through_hurdles)
+        });
+        app.add_system(apply_time_scale);
+    }
+}
+
+/// Something that would end the run (or cost a respawn) unless a power-up protects from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hazard {
+    HeadOnGround,
+    /// Protection from a hurdle means passing through it.
+    Hurdle,
+}
+
+/// A temporary effect on the player. The game systems only go through this trait, so adding a
+/// power-up only takes a new [`PowerUpKind`] and an implementation returned by [`power_up`].
+pub trait PowerUp: Sync {
+    fn name(&self) -> &'static str;
+
+    /// In seconds of race time.
+    fn duration(&self) -> f32;
+
+    fn stick_restitution_scale(&self) -> f32 {
+        1.0
+    }
+
+    /// Scales the physics timestep, and with it the race time.
+    fn time_scale(&self) -> f32 {
+        1.0
+    }
+
+    /// Power-ups that protect from a hazard are used up by it.
+    fn protects_from(&self, _hazard: Hazard) -> bool {
+        false
+    }
+}
+
+struct SuperSpring;
+
+impl PowerUp for SuperSpring {
+    fn name(&self) -> &'static str {
+        "Super Spring"
+    }
+
+    fn duration(&self) -> f32 {
+        8.0
+    }
+
+    fn stick_restitution_scale(&self) -> f32 {
+        1.3
+    }
+}
+
+struct Helmet;
+
+impl PowerUp for Helmet {
+    fn name(&self) -> &'static str {
+        "Helmet"
+    }
+
+    fn duration(&self) -> f32 {
+        15.0
+    }
+
+    fn protects_from(&self, hazard: Hazard) -> bool {
+        hazard == Hazard::HeadOnGround
+    }
+}
+
+struct Ghost;
+
+impl PowerUp for Ghost {
+    fn name(&self) -> &'static str {
+        "Ghost"
+    }
+
+    fn duration(&self) -> f32 {
+        10.0
+    }
+
+    fn protects_from(&self, hazard: Hazard) -> bool {
+        hazard == Hazard::Hurdle
+    }
+}
+
+struct SlowMo;
+
+impl PowerUp for SlowMo {
+    fn name(&self) -> &'static str {
+        "Slow-Mo"
+    }
+
+    fn duration(&self) -> f32 {
+        4.0
+    }
+
+    fn time_scale(&self) -> f32 {
+        0.5
+    }
+}
+
+pub fn power_up(kind: PowerUpKind) -> &'static dyn PowerUp {
+    match kind {
+        PowerUpKind::SuperSpring => &SuperSpring,
+        PowerUpKind::Helmet => &Helmet,
+        PowerUpKind::Ghost => &Ghost,
+        PowerUpKind::SlowMo => &SlowMo,
+    }
+}
+
+pub struct ActivePowerUp {
+    pub kind: PowerUpKind,
+    /// In race time.
+    pub expires_at: Duration,
+}
+
+#[derive(Default)]
+pub struct ActivePowerUps(Vec<ActivePowerUp>);
+
+impl ActivePowerUps {
+    /// Picking up a power-up that is already active restarts its duration.
+    pub fn activate(&mut self, kind: PowerUpKind, now: Duration) {
+        let expires_at = now + Duration::from_secs_f32(power_up(kind).duration());
+        if let Some(active) = self.0.iter_mut().find(|active| active.kind == kind) {
+            active.expires_at = expires_at;
+        } else {
+            self.0.push(ActivePowerUp { kind, expires_at });
+        }
+    }
+
+    /// Whether an active power-up protects the player from the hazard, using it up if it does.
+    pub fn protect_from(&mut self, hazard: Hazard) -> bool {
+        if let Some(index) = self
+            .0
+            .iter()
+            .position(|active| power_up(active.kind).protects_from(hazard))
+        {
+            self.0.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn stick_restitution_scale(&self) -> f32 {
+        self.0
+            .iter()
+            .map(|active| power_up(active.kind).stick_restitution_scale())
+            .product()
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.0
+            .iter()
+            .map(|active| power_up(active.kind).time_scale())
+            .product()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ActivePowerUp> {
+        self.0.iter()
+    }
+}
+
+fn reset_power_ups(mut active_power_ups: ResMut<ActivePowerUps>) {
+    *active_power_ups = ActivePowerUps::default();
+}
+
+fn expire_power_ups(
+    player_status: Res<PlayerStatus>,
+    mut active_power_ups: ResMut<ActivePowerUps>,
+) {
+    active_power_ups
+        .0
+        .retain(|active| player_status.time < active.expires_at);
+}
+
+fn fade_passed_through_hurdles(mut query: Query<(&Hurdle, &mut Sprite), Changed<Hurdle>>) {
+    for (hurdle, mut sprite) in query.iter_mut() {
+        if hurdle.passed_through {
+            sprite.color.set_a(0.4);
+        }
+    }
+}
+
+fn apply_time_scale(
+    active_power_ups: Res<ActivePowerUps>,
+    mut integration_parameters: ResMut<IntegrationParameters>,
+) {
+    let dt = IntegrationParameters::default().dt * active_power_ups.time_scale();
+    if integration_parameters.dt != dt {
+        integration_parameters.dt = dt;
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use ezinput::prelude::*;
+
+use crate::game_systems::pogo::{rider_stats, PogoStick};
+use crate::game_systems::power_ups::ActivePowerUps;
+use crate::global_types::{AppState, Ground, InputBinding, PlayerSprite, PlayerStatus};
+use crate::tuning::PogoTuning;
+use crate::utils::entities_ordered_by_type;
+
+pub struct PumpPlugin;
+
+impl Plugin for PumpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Pump>();
+        app.add_system_set(
+            SystemSet::on_enter(AppState::ClearLevelAndThenLoad).with_system(reset_pump),
+        );
+        app.add_system_set({
+            SystemSet::on_update(AppState::Game)
+                .with_system(charge_pump.label(ChargePump))
+                .with_system(release_pump_on_landing.after(ChargePump))
+        });
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct ChargePump;
+
+/// The spring of the pogo stick, compressed by holding the pump button. Letting go of the button
+/// right before the stick lands adds the charge to the bounce - letting go too early, or not at
+/// all, dampens the bounce instead.
+#[derive(Default)]
+pub struct Pump {
+    /// From 0 (not compressed at all) to 1 (fully compressed).
+    pub charge: f32,
+    held: bool,
+    /// In race time.
+    let_go_at: Option<Duration>,
+}
+
+fn reset_pump(mut pump: ResMut<Pump>) {
+    *pump = Pump::default();
+}
+
+fn charge_pump(
+    time: Res<Time>,
+    input_views: Query<&InputView<InputBinding>>,
+    player_status: Res<PlayerStatus>,
+    active_power_ups: Res<ActivePowerUps>,
+    pogo_tuning: Res<PogoTuning>,
+    mut pump: ResMut<Pump>,
+) {
+    let held = input_views
+        .iter()
+        .any(|input_view| !input_view.key(&InputBinding::Pump).released());
+    if held {
+        // Charge in race time, so that slow-mo doesn't make pumping easier
+        let delta = time.delta_seconds() * active_power_ups.time_scale();
+        let charge_time =
+            pogo_tuning.pump_charge_time * rider_stats(player_status.rider).pump_charge_time_scale;
+        pump.charge = (pump.charge + delta / charge_time).min(1.0);
+        pump.let_go_at = None;
+    } else if pump.held && 0.0 < pump.charge {
+        pump.let_go_at = Some(player_status.time);
+    }
+    pump.held = held;
+}
+
+fn release_pump_on_landing(
+    mut reader: EventReader<ContactEvent>,
+    stick_query: Query<(), With<PogoStick>>,
+    ground_query: Query<(), With<Ground>>,
+    mut player_query: Query<
+        (
+            &mut RigidBodyVelocityComponent,
+            &RigidBodyMassPropsComponent,
+        ),
+        With<PlayerSprite>,
+    >,
+    player_status: Res<PlayerStatus>,
+    pogo_tuning: Res<PogoTuning>,
+    mut pump: ResMut<Pump>,
+) {
+    for event in reader.iter() {
+        if let ContactEvent::Started(handle1, handle2) = event {
+            if entities_ordered_by_type!(
+                [handle1.entity(), handle2.entity()],
+                stick_query,
+                ground_query
+            )
+            .is_none()
+                || pump.charge <= 0.0
+            {
+                continue;
+            }
+            let timed_well = pump.let_go_at.is_some_and(|let_go_at| {
+                player_status.time.saturating_sub(let_go_at).as_secs_f32()
+                    <= pogo_tuning.pump_window
+            });
+            // The contact event comes after the physics step, so the stick already bounced
+            for (mut velocity, mass_props) in player_query.iter_mut() {
+                if timed_well {
+                    let impulse = pogo_tuning.pump_impulse * pump.charge;
+                    velocity.apply_impulse(mass_props, vector![0.0, impulse]);
+                } else if 0.0 < velocity.linvel.y {
+                    velocity.linvel.y *= 1.0 - pogo_tuning.mistimed_pump_damping * pump.charge;
+                }
+            }
+            pump.charge = 0.0;
+            pump.let_go_at = None;
+        }
+    }
+}
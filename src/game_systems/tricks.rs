@@ -0,0 +1,216 @@
+use std::f32::consts::{PI, TAU};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::game_systems::pogo::{PogoStick, RespawnPlayer};
+use crate::global_types::{AppState, Ground, PlayerHead, PlayerSprite, PlayerStatus};
+use crate::utils::entities_ordered_by_type;
+
+pub struct TricksPlugin;
+
+impl Plugin for TricksPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TrickLanded>();
+        app.init_resource::<Tricks>();
+        app.add_system_set(
+            SystemSet::on_enter(AppState::ClearLevelAndThenLoad).with_system(reset_tricks),
+        );
+        app.add_system_set({
+            SystemSet::on_update(AppState::Game)
+                .with_system(track_rotation.label(TrackRotation))
+                .with_system(score_landings.after(TrackRotation))
+        });
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct TrackRotation;
+
+/// Bounces shorter than this (in race time) are not jumps, and can't have tricks.
+const MIN_AIRTIME: f32 = 0.4;
+
+/// Landing closer than this to upright is a clean landing.
+const CLEAN_LANDING_ANGLE: f32 = 0.15;
+
+/// Landing further than this from upright is botched, and breaks the combo.
+const BOTCHED_LANDING_ANGLE: f32 = 0.6;
+
+const MAX_COMBO_MULTIPLIER: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trick {
+    /// Full turns in the air. Backwards is counter-clockwise, since the runners go to the right.
+    Flip {
+        turns: usize,
+        backwards: bool,
+    },
+    /// Going upside down and turning back, without completing a flip.
+    HalfSpin,
+    CleanLanding,
+}
+
+impl Trick {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Flip { turns, backwards } => {
+                let direction = if *backwards { "Backflip" } else { "Frontflip" };
+                match turns {
+                    1 => direction.to_owned(),
+                    2 => format!("Double {}", direction),
+                    3 => format!("Triple {}", direction),
+                    _ => format!("{}x {}", turns, direction),
+                }
+            }
+            Self::HalfSpin => "Half-Spin".to_owned(),
+            Self::CleanLanding => "Clean Landing".to_owned(),
+        }
+    }
+
+    pub fn points(&self) -> usize {
+        match self {
+            Self::Flip { turns, .. } => 100 * turns,
+            Self::HalfSpin => 50,
+            Self::CleanLanding => 10,
+        }
+    }
+}
+
+/// Sent when the player lands tricks. `points` already include the combo multiplier.
+pub struct TrickLanded {
+    pub tricks: Vec<Trick>,
+    pub points: usize,
+    pub multiplier: usize,
+}
+
+/// The rotation of the player since the last time the stick touched the ground, and the current
+/// combo.
+#[derive(Default)]
+pub struct Tricks {
+    previous_angle: Option<f32>,
+    /// Counter-clockwise is positive. Not wrapped, so it counts full turns.
+    rotation: f32,
+    /// The furthest the rotation got from where it started, in either direction.
+    furthest_rotation: f32,
+    /// In race time.
+    last_landing: Duration,
+    /// Landings with tricks since the combo was last broken.
+    combo: usize,
+}
+
+impl Tricks {
+    /// What the points of the next tricks will be multiplied by, if the combo continues.
+    pub fn multiplier(&self) -> usize {
+        (self.combo + 1).min(MAX_COMBO_MULTIPLIER)
+    }
+
+    fn start_jump(&mut self, now: Duration) {
+        self.rotation = 0.0;
+        self.furthest_rotation = 0.0;
+        self.last_landing = now;
+    }
+
+    fn break_combo(&mut self, now: Duration) {
+        self.combo = 0;
+        self.start_jump(now);
+    }
+
+    /// Score the jump that ends with landing at `angle`, and start the next one.
+    fn land(&mut self, angle: f32, now: Duration) -> Option<TrickLanded> {
+        let airtime = now.saturating_sub(self.last_landing).as_secs_f32();
+        let rotation = self.rotation;
+        let furthest_rotation = self.furthest_rotation;
+        self.start_jump(now);
+        if airtime < MIN_AIRTIME {
+            return None;
+        }
+        if BOTCHED_LANDING_ANGLE < angle.abs() {
+            self.combo = 0;
+            return None;
+        }
+
+        let mut tricks = Vec::new();
+        let turns = (rotation / TAU).round();
+        if turns != 0.0 {
+            tricks.push(Trick::Flip {
+                turns: turns.abs() as usize,
+                backwards: 0.0 < turns,
+            });
+        } else if PI <= furthest_rotation {
+            tricks.push(Trick::HalfSpin);
+        }
+        if angle.abs() <= CLEAN_LANDING_ANGLE {
+            tricks.push(Trick::CleanLanding);
+        }
+        if tricks.is_empty() {
+            return None;
+        }
+
+        let multiplier = self.multiplier();
+        self.combo += 1;
+        let points = tricks.iter().map(Trick::points).sum::<usize>() * multiplier;
+        Some(TrickLanded {
+            tricks,
+            points,
+            multiplier,
+        })
+    }
+}
+
+fn reset_tricks(mut tricks: ResMut<Tricks>) {
+    *tricks = Tricks::default();
+}
+
+fn track_rotation(
+    mut tricks: ResMut<Tricks>,
+    player_query: Query<&RigidBodyPositionComponent, With<PlayerSprite>>,
+) {
+    for position in player_query.iter() {
+        let angle = position.position.rotation.angle();
+        if let Some(previous_angle) = tricks.previous_angle {
+            // Angles are wrapped to -PI..PI, so take the short way between them
+            let delta = (angle - previous_angle + PI).rem_euclid(TAU) - PI;
+            tricks.rotation += delta;
+            tricks.furthest_rotation = tricks.furthest_rotation.max(tricks.rotation.abs());
+        }
+        tricks.previous_angle = Some(angle);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn score_landings(
+    mut contact_reader: EventReader<ContactEvent>,
+    mut respawn_reader: EventReader<RespawnPlayer>,
+    stick_query: Query<(), With<PogoStick>>,
+    head_query: Query<(), With<PlayerHead>>,
+    ground_query: Query<(), With<Ground>>,
+    player_query: Query<&RigidBodyPositionComponent, With<PlayerSprite>>,
+    mut tricks: ResMut<Tricks>,
+    mut player_status: ResMut<PlayerStatus>,
+    mut trick_writer: EventWriter<TrickLanded>,
+) {
+    let now = player_status.time;
+    if respawn_reader.iter().last().is_some() {
+        tricks.previous_angle = None;
+        tricks.break_combo(now);
+    }
+    for event in contact_reader.iter() {
+        if let ContactEvent::Started(handle1, handle2) = event {
+            let (entity1, entity2) = (handle1.entity(), handle2.entity());
+            if entities_ordered_by_type!([entity1, entity2], head_query, ground_query).is_some() {
+                tricks.break_combo(now);
+            } else if entities_ordered_by_type!([entity1, entity2], stick_query, ground_query)
+                .is_some()
+            {
+                for position in player_query.iter() {
+                    let angle = position.position.rotation.angle();
+                    if let Some(trick_landed) = tricks.land(angle, now) {
+                        player_status.trick_score += trick_landed.points;
+                        trick_writer.send(trick_landed);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::pogo::{PogoStick, StickInWater};
+use crate::global_types::{AppState, DespawnWithLevel, Lane, PlayerStatus, WaterPit};
+use crate::track::{Terrain, WaterPitDefinition};
+use crate::utils::entities_ordered_by_type;
+
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(detect_water_landing));
+    }
+}
+
+/// How high above the ground the water reaches.
+const WATER_DEPTH: f32 = 0.4;
+
+pub fn spawn_water_pit(
+    commands: &mut Commands,
+    terrain: &Terrain,
+    lane_layout: &LaneLayout,
+    lane: Lane,
+    start: f32,
+    definition: &WaterPitDefinition,
+) -> Entity {
+    let center = Vec2::new(
+        start + definition.length * 0.5,
+        terrain.height_at(start + definition.length * 0.5)
+            + lane_layout.offset(lane)
+            + WATER_DEPTH * 0.5,
+    );
+    let mut cmd = commands.spawn_bundle(ColliderBundle {
+        collider_type: ColliderType::Sensor.into(),
+        shape: ColliderShape::cuboid(definition.length * 0.5, WATER_DEPTH * 0.5).into(),
+        position: center.into(),
+        flags: ColliderFlags {
+            active_events: ActiveEvents::INTERSECTION_EVENTS,
+            collision_groups: lane_layout.ground_groups(lane),
+            ..Default::default()
+        }
+        .into(),
+        ..Default::default()
+    });
+    cmd.insert_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0.2, 0.4, 0.9, 0.6),
+            custom_size: Some(Vec2::new(definition.length, WATER_DEPTH)),
+            ..Default::default()
+        },
+        // In front of the player, so that the stick looks submerged
+        transform: Transform::from_xyz(center.x, center.y, 0.2 + lane_layout.z_offset(lane)),
+        ..Default::default()
+    });
+    cmd.insert(DespawnWithLevel);
+    cmd.insert(WaterPit {
+        restitution: definition.restitution,
+        friction: definition.friction,
+        penalty: definition.penalty,
+        splashed: false,
+    });
+    cmd.id()
+}
+
+fn detect_water_landing(
+    mut reader: EventReader<IntersectionEvent>,
+    mut water_pit_query: Query<&mut WaterPit>,
+    stick_query: Query<(), With<PogoStick>>,
+    mut player_status: ResMut<PlayerStatus>,
+    mut commands: Commands,
+) {
+    for event in reader.iter() {
+        if let Some([pit_entity, stick_entity]) = entities_ordered_by_type!(
+            [event.collider1.entity(), event.collider2.entity()],
+            water_pit_query,
+            stick_query
+        ) {
+            if event.intersecting {
+                let mut water_pit = water_pit_query.get_mut(pit_entity).unwrap();
+                commands.entity(stick_entity).insert(StickInWater {
+                    restitution: water_pit.restitution,
+                    friction: water_pit.friction,
+                });
+                if !water_pit.splashed {
+                    water_pit.splashed = true;
+                    player_status.penalty += Duration::from_secs_f32(water_pit.penalty);
+                }
+            } else {
+                commands.entity(stick_entity).remove::<StickInWater>();
+            }
+        }
+    }
+}
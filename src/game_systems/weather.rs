@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::prelude::SliceRandom;
+
+use crate::global_types::{AppState, DespawnWithLevel, PlayerSprite, RngStream, TrackSeed};
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeatherChoice>();
+        app.init_resource::<Weather>();
+        app.add_system_set(
+            SystemSet::on_enter(AppState::ClearLevelAndThenLoad).with_system(decide_weather),
+        );
+        app.add_system_set(
+            SystemSet::on_enter(AppState::LoadLevel).with_system(add_weather_overlay),
+        );
+        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(apply_air_damping));
+    }
+}
+
+/// The weather of the current race.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Weather {
+    #[default]
+    Dry,
+    Rain,
+    Heat,
+    Snow,
+}
+
+impl Weather {
+    pub const ALL: [Self; 4] = [Self::Dry, Self::Rain, Self::Heat, Self::Snow];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Dry => "Dry",
+            Self::Rain => "Rain",
+            Self::Heat => "Heat",
+            Self::Snow => "Snow",
+        }
+    }
+
+    pub fn stick_restitution_scale(&self) -> f32 {
+        match self {
+            Self::Dry => 1.0,
+            Self::Rain => 0.9,
+            Self::Heat => 1.1,
+            Self::Snow => 0.8,
+        }
+    }
+
+    /// Less friction makes the stick slide when landing at an angle.
+    pub fn stick_friction_scale(&self) -> f32 {
+        match self {
+            Self::Dry => 1.0,
+            Self::Rain => 0.5,
+            Self::Heat => 1.0,
+            Self::Snow => 0.3,
+        }
+    }
+
+    pub fn competitor_speed_scale(&self) -> f32 {
+        match self {
+            Self::Dry => 1.0,
+            Self::Rain => 0.9,
+            Self::Heat => 0.85,
+            Self::Snow => 0.75,
+        }
+    }
+
+    /// The linear damping of the player's body.
+    pub fn air_damping(&self) -> f32 {
+        match self {
+            Self::Dry => 0.0,
+            Self::Rain => 0.15,
+            Self::Heat => 0.0,
+            Self::Snow => 0.25,
+        }
+    }
+
+    /// Drawn over the whole screen.
+    fn tint(&self) -> Color {
+        match self {
+            Self::Dry => Color::NONE,
+            Self::Rain => Color::rgba(0.2, 0.3, 0.5, 0.25),
+            Self::Heat => Color::rgba(1.0, 0.6, 0.2, 0.15),
+            Self::Snow => Color::rgba(0.9, 0.95, 1.0, 0.3),
+        }
+    }
+}
+
+/// The weather picked in the race setup screen. Kept between races.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeatherChoice {
+    /// Decided by the track seed, so retrying the track keeps its weather.
+    Random,
+    Fixed(Weather),
+}
+
+impl Default for WeatherChoice {
+    fn default() -> Self {
+        Self::Fixed(Weather::Dry)
+    }
+}
+
+impl WeatherChoice {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Random => "Random",
+            Self::Fixed(weather) => weather.label(),
+        }
+    }
+}
+
+fn decide_weather(
+    mut weather: ResMut<Weather>,
+    weather_choice: Res<WeatherChoice>,
+    track_seed: Res<TrackSeed>,
+) {
+    *weather = match *weather_choice {
+        WeatherChoice::Random => *Weather::ALL
+            .choose(&mut track_seed.rng(RngStream::Weather))
+            .unwrap(),
+        WeatherChoice::Fixed(weather) => weather,
+    };
+}
+
+fn add_weather_overlay(mut commands: Commands, weather: Res<Weather>) {
+    if *weather == Weather::Dry {
+        return;
+    }
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: weather.tint().into(),
+            ..Default::default()
+        })
+        .insert(DespawnWithLevel);
+}
+
+fn apply_air_damping(
+    weather: Res<Weather>,
+    mut query: Query<&mut RigidBodyDampingComponent, With<PlayerSprite>>,
+) {
+    let linear_damping = weather.air_damping();
+    for mut damping in query.iter_mut() {
+        if damping.linear_damping != linear_damping {
+            damping.linear_damping = linear_damping;
+        }
+    }
+}
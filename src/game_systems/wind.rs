@@ -0,0 +1,272 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::game_systems::hurdles::distribute_distances;
+use crate::game_systems::lanes::LaneLayout;
+use crate::game_systems::power_ups::ActivePowerUps;
+use crate::global_types::{
+    AppState, Competitor, DespawnWithLevel, PlayerSprite, PlayerStatus, RngStream, TrackSeed,
+};
+use crate::track::{
+    CurrentTrack, GustSettings, LoadCurrentTrack, Terrain, TrackDefinition, WindForce, WindZone,
+    WindZoneLayout,
+};
+use crate::utils::random_between;
+
+pub struct WindPlugin;
+
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Wind>();
+        app.add_system_set(
+            SystemSet::on_enter(AppState::ClearLevelAndThenLoad)
+                .with_system(reset_wind.after(LoadCurrentTrack)),
+        );
+        app.add_system_set(SystemSet::on_enter(AppState::LoadLevel).with_system(add_wind_zones));
+        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(blow_wind));
+    }
+}
+
+/// The wind of the current track.
+#[derive(Default)]
+pub struct Wind {
+    /// Endless tracks add zones as they generate chunks.
+    pub zones: Vec<WindZone>,
+    gusts: Option<Gusts>,
+    affects_competitors: bool,
+    blows: bool,
+}
+
+impl Wind {
+    /// Whether the current track has any wind at all.
+    pub fn blows(&self) -> bool {
+        self.blows
+    }
+
+    pub fn force_at(&self, x: f32, time: Duration) -> Vec2 {
+        let zones_force = self
+            .zones
+            .iter()
+            .filter(|zone| zone.start <= x && x < zone.end)
+            .fold(Vec2::ZERO, |force, zone| force + Vec2::from(zone.force));
+        let gust_force = self
+            .gusts
+            .as_ref()
+            .map_or(Vec2::ZERO, |gusts| gusts.force_at(time.as_secs_f32()));
+        zones_force + gust_force
+    }
+}
+
+/// Keeps a track with zero-length gusts from scheduling infinitely many of them at once.
+const MIN_GUST_DURATION: f32 = 0.1;
+
+#[derive(Clone, Copy)]
+struct Gust {
+    start: f32,
+    end: f32,
+    force: Vec2,
+}
+
+/// Gusts are scheduled one after the other from the same RNG, in race time, so the same seed always
+/// blows the same gusts at the same times.
+struct Gusts {
+    settings: GustSettings,
+    rng: StdRng,
+    current: Gust,
+}
+
+impl Gusts {
+    fn new(settings: GustSettings, rng: StdRng) -> Self {
+        let mut gusts = Self {
+            settings,
+            rng,
+            current: Gust {
+                start: 0.0,
+                end: 0.0,
+                force: Vec2::ZERO,
+            },
+        };
+        gusts.current = gusts.gust_after(0.0);
+        gusts
+    }
+
+    fn gust_after(&mut self, time: f32) -> Gust {
+        let settings = self.settings;
+        let start =
+            time + random_between(&mut self.rng, settings.min_interval, settings.max_interval);
+        let duration = random_between(&mut self.rng, settings.min_duration, settings.max_duration);
+        Gust {
+            start,
+            end: start + duration.max(MIN_GUST_DURATION),
+            force: random_force(&mut self.rng, settings.max_force),
+        }
+    }
+
+    fn advance_to(&mut self, time: f32) {
+        while self.current.end <= time {
+            self.current = self.gust_after(self.current.end);
+        }
+    }
+
+    fn force_at(&self, time: f32) -> Vec2 {
+        let Gust { start, end, force } = self.current;
+        if (start..end).contains(&time) {
+            force * (PI * (time - start) / (end - start)).sin()
+        } else {
+            Vec2::ZERO
+        }
+    }
+}
+
+fn random_force(rng: &mut impl Rng, max_force: WindForce) -> Vec2 {
+    Vec2::new(
+        max_force.x * rng.gen_range(-1.0..=1.0),
+        max_force.y * rng.gen_range(-1.0..=1.0),
+    )
+}
+
+/// The wind zones of the track. Generated zones are only spread over the track bounds.
+pub fn wind_zones(track: &TrackDefinition, track_seed: &TrackSeed) -> Vec<WindZone> {
+    match &track.wind.zones {
+        WindZoneLayout::Explicit(zones) => zones.clone(),
+        WindZoneLayout::Generated {
+            before_first,
+            zone_length,
+            count,
+            max_force,
+        } => {
+            let starts = distribute_distances(
+                &mut track_seed.rng(RngStream::WindZonePlacement),
+                *count,
+                *before_first..track.bounds.right,
+                *zone_length,
+            );
+            let mut rng = track_seed.rng(RngStream::WindZoneForces);
+            starts
+                .into_iter()
+                .map(|start| {
+                    let force = random_force(&mut rng, *max_force);
+                    WindZone {
+                        start,
+                        end: start + zone_length,
+                        force: WindForce {
+                            x: force.x,
+                            y: force.y,
+                        },
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+/// How high above the ground wind zones are drawn.
+const WIND_ZONE_HEIGHT: f32 = 6.0;
+
+/// Wind zones are drawn as a faint band behind all the lanes.
+pub fn spawn_wind_zone(
+    commands: &mut Commands,
+    terrain: &Terrain,
+    lane_layout: &LaneLayout,
+    zone: &WindZone,
+) -> Entity {
+    let behind_lanes = lane_layout
+        .lanes()
+        .map(|lane| lane_layout.z_offset(lane))
+        .fold(0.0, f32::min);
+    let ground = 0.5 * (terrain.height_at(zone.start) + terrain.height_at(zone.end))
+        + lane_layout.offset(lane_layout.player_lane());
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.8, 0.9, 1.0, 0.15),
+                custom_size: Some(Vec2::new(zone.end - zone.start, WIND_ZONE_HEIGHT)),
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(
+                0.5 * (zone.start + zone.end),
+                ground + 0.5 * WIND_ZONE_HEIGHT,
+                behind_lanes - 0.5,
+            ),
+            ..Default::default()
+        })
+        .insert(DespawnWithLevel)
+        .id()
+}
+
+fn reset_wind(
+    mut wind: ResMut<Wind>,
+    current_track: Res<CurrentTrack>,
+    track_seed: Res<TrackSeed>,
+) {
+    let track = &current_track.0;
+    let zones = wind_zones(track, &track_seed);
+    let endless_wind = track
+        .endless
+        .as_ref()
+        .is_some_and(|endless| 0.0 < endless.max_wind);
+    *wind = Wind {
+        blows: !zones.is_empty() || track.wind.gusts.is_some() || endless_wind,
+        zones,
+        gusts: track
+            .wind
+            .gusts
+            .map(|settings| Gusts::new(settings, track_seed.rng(RngStream::Gusts))),
+        affects_competitors: track.wind.affects_competitors,
+    };
+}
+
+fn add_wind_zones(
+    mut commands: Commands,
+    wind: Res<Wind>,
+    current_track: Res<CurrentTrack>,
+    terrain: Res<Terrain>,
+    lane_layout: Res<LaneLayout>,
+) {
+    if current_track.0.endless.is_some() {
+        // Endless tracks spawn their wind zones with the chunks they are in
+        return;
+    }
+    for zone in wind.zones.iter() {
+        spawn_wind_zone(&mut commands, &terrain, &lane_layout, zone);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn blow_wind(
+    time: Res<Time>,
+    player_status: Res<PlayerStatus>,
+    active_power_ups: Res<ActivePowerUps>,
+    mut wind: ResMut<Wind>,
+    mut player_query: Query<
+        (&GlobalTransform, &mut RigidBodyVelocityComponent),
+        With<PlayerSprite>,
+    >,
+    mut competitors_query: Query<
+        (&GlobalTransform, &mut RigidBodyVelocityComponent),
+        (With<Competitor>, Without<PlayerSprite>),
+    >,
+) {
+    if let Some(gusts) = wind.gusts.as_mut() {
+        gusts.advance_to(player_status.time.as_secs_f32());
+    }
+    let delta = time.delta_seconds() * active_power_ups.time_scale();
+    let blow = |transform: &GlobalTransform, velocity: &mut RigidBodyVelocityComponent| {
+        let force = wind.force_at(transform.translation.x, player_status.time);
+        let delta_velocity: Vector<f32> = (force * delta).into();
+        velocity.linvel += delta_velocity;
+    };
+    for (transform, mut velocity) in player_query.iter_mut() {
+        blow(transform, &mut velocity);
+    }
+    if wind.affects_competitors {
+        for (transform, mut velocity) in competitors_query.iter_mut() {
+            blow(transform, &mut velocity);
+        }
+    }
+}
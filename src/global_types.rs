@@ -3,6 +3,8 @@ use std::time::Duration;
 use bevy::prelude::*;
 use ezinput_macros::BindingTypeView;
 use ezinput::prelude::BindingTypeView;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 #[derive(Hash, Debug, PartialEq, Eq, Clone)]
 pub enum AppState {
@@ -10,11 +12,17 @@ pub enum AppState {
     ClearLevelAndThenLoad,
     LoadLevel,
     Game,
+    /// The player crashed, and the ragdoll tumbles for a bit before the game over menu.
+    Crash,
+    Editor,
 }
 
 #[derive(Hash, Debug, PartialEq, Eq, Clone)]
 pub enum MenuState {
     Main,
+    RiderSelect,
+    RaceSetup,
+    Settings,
     Pause,
     GameOver,
 }
@@ -30,21 +38,100 @@ pub enum GameOver {
 #[derive(BindingTypeView, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum InputBinding {
     Rotate,
+    Pump,
     Pause,
 }
 
+#[derive(Default)]
 pub struct GameBoundaries {
     pub left: f32,
     pub right: f32,
 }
 
-impl GameBoundaries {
-    pub fn width(&self) -> f32 {
-        self.right - self.left
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TrackSeed(pub u64);
+
+/// Separate random streams, so that adding randomness to one part of the level generation does not
+/// change the layout of another part.
+#[derive(Debug, Clone, Copy)]
+pub enum RngStream {
+    HurdlePlacement,
+    HurdleDimensions,
+    SurfacePlacement,
+    SurfaceTypes,
+    EndlessTrack,
+    WindZonePlacement,
+    WindZoneForces,
+    Gusts,
+    PickupPlacement,
+    PickupTypes,
+    Weather,
+}
+
+impl TrackSeed {
+    pub fn random() -> Self {
+        Self(rand::random())
     }
 
-    pub fn center(&self) -> f32 {
-        (self.right + self.left) * 0.5
+    pub fn rng(&self, stream: RngStream) -> StdRng {
+        StdRng::seed_from_u64(self.0 ^ (stream as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+}
+
+/// How much the game helps the player stay upright. Picked in the settings screen, and recorded with
+/// the results of every race.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AssistLevel {
+    Off,
+    Light,
+    #[default]
+    Standard,
+    Strong,
+}
+
+impl AssistLevel {
+    pub const ALL: [Self; 4] = [Self::Off, Self::Light, Self::Standard, Self::Strong];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Light => "Light",
+            Self::Standard => "Standard",
+            Self::Strong => "Strong",
+        }
+    }
+}
+
+/// The playable riders. Picked in the rider select screen, and recorded with the results of every
+/// race.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Rider {
+    #[default]
+    Classic,
+    Heavy,
+    Light,
+    Bouncy,
+}
+
+impl Rider {
+    pub const ALL: [Self; 4] = [Self::Classic, Self::Heavy, Self::Light, Self::Bouncy];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Classic => "Classic",
+            Self::Heavy => "Heavy",
+            Self::Light => "Light",
+            Self::Bouncy => "Bouncy",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Classic => "Balanced in every way",
+            Self::Heavy => "Heavy and stable, but slow to turn and pump",
+            Self::Light => "Light and twitchy, flips at the slightest touch",
+            Self::Bouncy => "Bounces higher, and harder to land",
+        }
     }
 }
 
@@ -54,11 +141,25 @@ pub struct PlayerStatus {
     pub time: Duration,
     pub competitors_before: usize,
     pub competitors_after: usize,
+    pub hurdles_knocked: usize,
+    pub penalty: Duration,
+    pub respawns: usize,
+    /// One point for every coin and time bonus collected.
+    pub score: usize,
+    pub time_bonus: Duration,
+    /// Points from aerial tricks, combo multipliers included.
+    pub trick_score: usize,
+    /// The assist level the race was started with.
+    pub assist_level: AssistLevel,
+    /// The rider the race was started with.
+    pub rider: Rider,
 }
 
 impl PlayerStatus {
     pub fn format_time(&self) -> String {
-        let time_in_seconds = self.time.as_secs_f32();
+        let time_in_seconds = (self.time + self.penalty)
+            .saturating_sub(self.time_bonus)
+            .as_secs_f32();
         let only_minutes = time_in_seconds as u32 / 60;
         let only_seconds = time_in_seconds % 60.0;
         format!("{:02}:{:02.1}", only_minutes, only_seconds)
@@ -89,10 +190,37 @@ pub struct Player;
 pub struct PlayerHead;
 
 #[derive(Component)]
-pub struct Hurdle;
+pub struct Hurdle {
+    pub width: f32,
+    pub height: f32,
+    pub knocked_down: bool,
+    /// Passed through with a power-up, so it no longer stops anything.
+    pub passed_through: bool,
+}
+
+/// A hurdle followed by a water pit.
+#[derive(Component)]
+pub struct WaterJump {
+    pub pit_length: f32,
+}
+
+#[derive(Component)]
+pub struct WaterPit {
+    pub restitution: f32,
+    pub friction: f32,
+    pub penalty: f32,
+    pub splashed: bool,
+}
+
+/// A bar hanging over the track, that injures the player when touched with the head.
+#[derive(Component)]
+pub struct OverheadBar;
 
 #[derive(Component)]
 pub struct Competitor;
 
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Lane(pub usize);
+
 #[derive(Component)]
 pub struct Ground;
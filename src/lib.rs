@@ -1,21 +1,27 @@
 mod audio;
 mod consts;
+mod editor;
 mod game_systems;
 mod global_types;
 mod loading;
+mod track;
+mod tuning;
 mod ui;
 mod utils;
 
 use crate::audio::InternalAudioPlugin;
 use crate::loading::LoadingPlugin;
+use crate::track::TrackPlugin;
+use crate::tuning::TuningPlugin;
 
 use bevy::app::App;
 #[cfg(debug_assertions)]
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
 
-use self::consts::TRACK_LENGTH;
-use self::global_types::{AppState, GameBoundaries, GameOver, MenuState, PlayerStatus};
+use self::global_types::{
+    AppState, AssistLevel, GameBoundaries, GameOver, MenuState, PlayerStatus, Rider, TrackSeed,
+};
 
 pub struct GamePlugin;
 
@@ -23,19 +29,22 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state(AppState::Menu(MenuState::Main));
         app.add_state::<Option<GameOver>>(None);
-        app.insert_resource(GameBoundaries {
-            left: -10.0,
-            right: TRACK_LENGTH,
-        });
+        app.init_resource::<GameBoundaries>();
         app.init_resource::<PlayerStatus>();
+        app.init_resource::<AssistLevel>();
+        app.init_resource::<Rider>();
+        app.insert_resource(TrackSeed::random());
+        app.add_plugin(TrackPlugin);
+        app.add_plugin(TuningPlugin);
         app.add_plugin(LoadingPlugin);
         app.add_plugin(InternalAudioPlugin);
         app.add_plugin(ui::UiPlugin);
         app.add_plugin(game_systems::GameSystemsPlugin);
+        app.add_plugin(editor::EditorPlugin);
 
         #[cfg(debug_assertions)]
         {
-            app.add_plugin(FrameTimeDiagnosticsPlugin::default());
+            app.add_plugin(FrameTimeDiagnosticsPlugin);
             app.add_plugin(LogDiagnosticsPlugin::default());
         }
     }
@@ -2,7 +2,12 @@ use std::time::Duration;
 
 use benimator::SpriteSheetAnimation;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy_asset_loader::{AssetCollection, AssetCollectionApp};
+
+use crate::global_types::Rider;
+use crate::track::TrackDefinition;
+use crate::tuning::PogoTuning;
 // use bevy_kira_audio::AudioSource;
 
 pub struct LoadingPlugin;
@@ -11,6 +16,8 @@ impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
         app.init_collection::<FontAssets>();
         app.init_collection::<TextureAssets>();
+        app.init_collection::<TrackAssets>();
+        app.init_collection::<TuningAssets>();
         app.add_startup_system(init_animations);
         app.init_resource::<AnimationAssets>();
     }
@@ -22,6 +29,7 @@ pub struct FontAssets {
     pub fira_sans: Handle<Font>,
 }
 
+#[allow(dead_code)]
 #[derive(AssetCollection)]
 pub struct AudioAssets {}
 
@@ -29,16 +37,52 @@ pub struct AudioAssets {}
 pub struct TextureAssets {
     #[asset(path = "sprites/pogo-player.png")]
     pub pogo_player: Handle<Image>,
+    #[asset(path = "sprites/pogo-player-heavy.png")]
+    pub pogo_player_heavy: Handle<Image>,
+    #[asset(path = "sprites/pogo-player-light.png")]
+    pub pogo_player_light: Handle<Image>,
+    #[asset(path = "sprites/pogo-player-bouncy.png")]
+    pub pogo_player_bouncy: Handle<Image>,
     #[asset(path = "sprites/hurdle.png")]
     pub hurdle: Handle<Image>,
     #[asset(path = "sprites/competitor.png")]
     pub competitor: Handle<Image>,
 }
 
+#[derive(AssetCollection)]
+pub struct TrackAssets {
+    #[asset(path = "tracks/hurdles-60.track.ron")]
+    pub hurdles_60: Handle<TrackDefinition>,
+    #[asset(path = "tracks/hurdles-110.track.ron")]
+    pub hurdles_110: Handle<TrackDefinition>,
+    /// The 200m hurdles.
+    #[asset(path = "tracks/standard.track.ron")]
+    pub standard: Handle<TrackDefinition>,
+    #[asset(path = "tracks/hurdles-400.track.ron")]
+    pub hurdles_400: Handle<TrackDefinition>,
+    #[asset(path = "tracks/endless.track.ron")]
+    pub endless: Handle<TrackDefinition>,
+}
+
+#[derive(AssetCollection)]
+pub struct TuningAssets {
+    #[asset(path = "tuning/pogo.tuning.ron")]
+    pub pogo: Handle<PogoTuning>,
+}
+
 #[derive(Default)]
 pub struct AnimationAssets {
     pub competitor_atlas: Handle<TextureAtlas>,
     pub competitor: Handle<SpriteSheetAnimation>,
+    /// Each rider's sprite split into the rider (index 0) and the pogo stick (index 1), so that the
+    /// two can come apart in a crash.
+    player_atlases: HashMap<Rider, Handle<TextureAtlas>>,
+}
+
+impl AnimationAssets {
+    pub fn player_atlas(&self, rider: Rider) -> Handle<TextureAtlas> {
+        self.player_atlases[&rider].clone()
+    }
 }
 
 fn init_animations(
@@ -57,4 +101,24 @@ fn init_animations(
         0..=3,
         Duration::from_millis(200),
     ));
+    for rider in Rider::ALL {
+        let texture = match rider {
+            Rider::Classic => &texture_assets.pogo_player,
+            Rider::Heavy => &texture_assets.pogo_player_heavy,
+            Rider::Light => &texture_assets.pogo_player_light,
+            Rider::Bouncy => &texture_assets.pogo_player_bouncy,
+        };
+        let mut player_atlas = TextureAtlas::new_empty(texture.clone(), Vec2::new(64.0, 64.0));
+        player_atlas.add_texture(bevy::sprite::Rect {
+            min: Vec2::new(0.0, 0.0),
+            max: Vec2::new(64.0, 48.0),
+        });
+        player_atlas.add_texture(bevy::sprite::Rect {
+            min: Vec2::new(0.0, 48.0),
+            max: Vec2::new(64.0, 64.0),
+        });
+        animation_assets
+            .player_atlases
+            .insert(rider, texture_atlases.add(player_atlas));
+    }
 }
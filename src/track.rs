@@ -0,0 +1,684 @@
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::{Deserialize, Serialize};
+
+use crate::consts::{HURDLE_HEIGHT, HURDLE_WIDTH};
+use crate::global_types::{AppState, GameBoundaries};
+use crate::loading::TrackAssets;
+
+pub struct TrackPlugin;
+
+impl Plugin for TrackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<TrackDefinition>();
+        app.init_asset_loader::<TrackDefinitionLoader>();
+        app.init_resource::<CurrentTrack>();
+        app.init_resource::<Terrain>();
+        app.init_resource::<RaceDistance>();
+        app.add_startup_system(select_default_track);
+        app.add_system_set(
+            SystemSet::on_enter(AppState::ClearLevelAndThenLoad)
+                .with_system(load_current_track.label(LoadCurrentTrack)),
+        );
+    }
+}
+
+/// Systems that read [`CurrentTrack`] when entering `ClearLevelAndThenLoad` must run after this.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LoadCurrentTrack;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "3a0c9f6e-4d2b-4c7e-9f1a-6b8e2d5c7a41"]
+pub struct TrackDefinition {
+    pub name: String,
+    pub bounds: TrackBounds,
+    #[serde(default)]
+    pub hurdles: HurdleLayout,
+    pub competitors: Vec<CompetitorDefinition>,
+    /// The height profile of the ground. Flat if empty, and extended flat to the track bounds.
+    #[serde(default)]
+    pub terrain: Vec<TerrainPoint>,
+    #[serde(default)]
+    pub hurdle_rules: HurdleRules,
+    #[serde(default)]
+    pub surfaces: SurfaceLayout,
+    /// Give each runner a separate lane. Without this, all the runners share the same lane.
+    #[serde(default)]
+    pub lanes: Option<LaneSettings>,
+    /// Only used when playing with checkpoints.
+    #[serde(default)]
+    pub checkpoints: CheckpointSettings,
+    /// Make the track go on forever, generating it in chunks as the player advances. The bounds
+    /// only mark where the terrain profile ends and the generated terrain begins.
+    #[serde(default)]
+    pub endless: Option<EndlessSettings>,
+    #[serde(default)]
+    pub wind: WindSettings,
+    #[serde(default)]
+    pub pickups: PickupLayout,
+}
+
+/// How endless tracks are generated. Difficulty ramps up linearly over `ramp_distance`: hurdles get
+/// closer together (from `start_spacing` down to `min_spacing`), taller (up to `max_height_scale`
+/// times their size in `mix`) and the terrain gets hillier (up to `max_hill_height`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndlessSettings {
+    pub chunk_length: f32,
+    /// Chunks are generated this far ahead of the player, and despawned this far behind.
+    pub view_distance: f32,
+    pub before_first: f32,
+    pub start_spacing: f32,
+    pub min_spacing: f32,
+    pub ramp_distance: f32,
+    pub max_height_scale: f32,
+    pub max_hill_height: f32,
+    /// Like the mix of [`HurdleLayout::Generated`]. An empty `mix` means all the hurdles are
+    /// standard.
+    #[serde(default)]
+    pub mix: Vec<HurdleMixEntry>,
+    /// Half of the chunks get a wind zone, blowing forward or backward with up to `max_wind` times
+    /// the difficulty.
+    #[serde(default)]
+    pub max_wind: f32,
+}
+
+/// Checkpoints are placed between hurdles, after every `every_hurdles` hurdles. Injuries and fouls
+/// respawn the player at the last checkpoint, for a time penalty of `penalty` seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckpointSettings {
+    pub every_hurdles: usize,
+    pub penalty: f32,
+}
+
+impl Default for CheckpointSettings {
+    fn default() -> Self {
+        Self {
+            every_hurdles: 3,
+            penalty: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PickupLayout {
+    /// Pickups at these exact places.
+    Explicit(Vec<PickupDefinition>),
+    /// Pickups randomly spread (based on the track seed) over the track, at least `spacing` apart and
+    /// `min_height` to `max_height` above the ground, with their kinds picked randomly from `mix`
+    /// according to the weights. Pickups over hurdles are raised so that jumping over the hurdle
+    /// collects them, and pickups under overhead bars are lowered so that they can be reached.
+    Generated {
+        before_first: f32,
+        spacing: f32,
+        min_height: f32,
+        max_height: f32,
+        mix: Vec<PickupMixEntry>,
+    },
+}
+
+impl Default for PickupLayout {
+    fn default() -> Self {
+        Self::Explicit(Vec::new())
+    }
+}
+
+impl PickupLayout {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Explicit(pickups) => pickups.is_empty(),
+            Self::Generated { mix, .. } => mix.is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PickupDefinition {
+    pub position: f32,
+    /// Above the ground.
+    pub height: f32,
+    pub kind: PickupKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PickupKind {
+    Coin,
+    /// Takes `seconds` off the race time.
+    TimeBonus {
+        seconds: f32,
+    },
+    PowerUp(PowerUpKind),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    /// Makes the stick bouncier.
+    SuperSpring,
+    /// Survives hitting the ground with the head once.
+    Helmet,
+    /// Passes through one hurdle.
+    Ghost,
+    /// Slows down the physics.
+    SlowMo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickupMixEntry {
+    pub kind: PickupKind,
+    pub weight: f32,
+}
+
+/// Wind pushes the runners, accelerating them by its force regardless of their mass. Zones blow over
+/// a part of the track, while gusts blow over the entire track from time to time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindSettings {
+    pub zones: WindZoneLayout,
+    pub gusts: Option<GustSettings>,
+    /// Push the competitors too, and not just the player.
+    pub affects_competitors: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct WindForce {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl From<WindForce> for Vec2 {
+    fn from(force: WindForce) -> Self {
+        Vec2::new(force.x, force.y)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WindZoneLayout {
+    /// Wind zones at these exact ranges.
+    Explicit(Vec<WindZone>),
+    /// `count` zones of `zone_length` randomly spread (based on the track seed) over the track, each
+    /// with a random force of up to `max_force` in either direction.
+    Generated {
+        before_first: f32,
+        zone_length: f32,
+        count: usize,
+        max_force: WindForce,
+    },
+}
+
+impl Default for WindZoneLayout {
+    fn default() -> Self {
+        Self::Explicit(Vec::new())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WindZone {
+    pub start: f32,
+    pub end: f32,
+    pub force: WindForce,
+}
+
+/// Gusts start every `min_interval` to `max_interval` seconds of race time, and last `min_duration`
+/// to `max_duration` seconds. Each gust has a random force of up to `max_force` in either direction,
+/// which builds up and dies down smoothly.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GustSettings {
+    pub min_interval: f32,
+    pub max_interval: f32,
+    pub min_duration: f32,
+    pub max_duration: f32,
+    pub max_force: WindForce,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LaneSettings {
+    /// How much higher each lane is drawn than the one in front of it.
+    pub spacing: f32,
+    /// The competitors take the other lanes, in order.
+    #[serde(default)]
+    pub player_lane: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum HurdleRules {
+    /// Any contact with a hurdle disqualifies.
+    #[default]
+    Strict,
+    /// Hurdles can be knocked over for a time penalty of `penalty` seconds. Only hitting a hurdle
+    /// with the body while moving forward faster than `disqualify_speed` disqualifies.
+    KnockDown { penalty: f32, disqualify_speed: f32 },
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TrackBounds {
+    pub left: f32,
+    pub right: f32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TerrainPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SurfaceType {
+    Normal,
+    /// Bouncier than the normal surface.
+    Trampoline,
+    /// Dampens the bounce.
+    Mud,
+    /// Almost no friction.
+    Ice,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SurfaceLayout {
+    /// Surface patches at these exact ranges. Everything else is normal.
+    Explicit(Vec<SurfacePatch>),
+    /// `count` patches of `patch_length` randomly spread (based on the track seed) over the track,
+    /// with their surface types picked randomly from `mix` according to the weights.
+    Generated {
+        before_first: f32,
+        patch_length: f32,
+        count: usize,
+        mix: Vec<SurfaceMixEntry>,
+    },
+}
+
+impl Default for SurfaceLayout {
+    fn default() -> Self {
+        Self::Explicit(Vec::new())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SurfacePatch {
+    pub start: f32,
+    pub end: f32,
+    pub surface: SurfaceType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurfaceMixEntry {
+    pub surface: SurfaceType,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HurdleLayout {
+    /// Hurdles at these exact positions.
+    Explicit(Vec<HurdleDefinition>),
+    /// Hurdles randomly spread (based on the track seed) over the track, at least `spacing` apart -
+    /// not counting the longest water pit or overhead bar in the `mix`.
+    ///
+    /// The dimensions of each hurdle are picked randomly from `mix`, according to the weights. An
+    /// empty `mix` means all the hurdles are standard.
+    Generated {
+        before_first: f32,
+        spacing: f32,
+        #[serde(default)]
+        mix: Vec<HurdleMixEntry>,
+    },
+    /// Hurdles randomly placed (based on the track seed) in sections that follow a difficulty
+    /// profile.
+    Profiled(DifficultyProfile),
+}
+
+/// Hurdles are placed in sections - easy ones with a single hurdle and a wide gap after it, rhythm
+/// sections of evenly spaced hurdles and double hurdles. Hard sections are always followed by a
+/// recovery gap. The track gets harder towards its end: hard sections become more likely (up to
+/// their weights, compared to easy sections that start at weight 1 and go down to 0) and the gaps
+/// get shorter.
+///
+/// Gaps are measured from where a hurdle and everything attached to it end to the position of the
+/// next hurdle, and are always between `min_gap` and `max_gap`. Hurdle dimensions are picked like in
+/// [`HurdleLayout::Generated`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DifficultyProfile {
+    pub before_first: f32,
+    pub min_gap: f32,
+    pub max_gap: f32,
+    /// Only easy sections start before this distance.
+    #[serde(default)]
+    pub warmup: f32,
+    pub rhythm_weight: f32,
+    pub double_weight: f32,
+    #[serde(default)]
+    pub mix: Vec<HurdleMixEntry>,
+}
+
+impl Default for HurdleLayout {
+    fn default() -> Self {
+        Self::Explicit(Vec::new())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HurdleDimensions {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for HurdleDimensions {
+    fn default() -> Self {
+        Self {
+            width: HURDLE_WIDTH,
+            height: HURDLE_HEIGHT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HurdleDefinition {
+    pub position: f32,
+    #[serde(default)]
+    pub dimensions: HurdleDimensions,
+    #[serde(default)]
+    pub motion: Option<ObstacleMotion>,
+    #[serde(default)]
+    pub water_pit: Option<WaterPitDefinition>,
+    #[serde(default)]
+    pub overhead_bar: Option<OverheadBarDefinition>,
+}
+
+impl HurdleDefinition {
+    /// Where the hurdle and everything attached to it end.
+    pub fn cleared_at(&self) -> f32 {
+        let hurdle_end = self.position + 0.5 * self.dimensions.width;
+        let pit_end = hurdle_end + self.water_pit.map_or(0.0, |water_pit| water_pit.length);
+        let bar_end = self.overhead_bar.map_or(f32::NEG_INFINITY, |overhead_bar| {
+            self.position + overhead_bar.offset + 0.5 * overhead_bar.length
+        });
+        pit_end.max(bar_end)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HurdleMixEntry {
+    pub dimensions: HurdleDimensions,
+    #[serde(default)]
+    pub motion: Option<ObstacleMotion>,
+    #[serde(default)]
+    pub water_pit: Option<WaterPitDefinition>,
+    #[serde(default)]
+    pub overhead_bar: Option<OverheadBarDefinition>,
+    pub weight: f32,
+}
+
+impl HurdleMixEntry {
+    pub fn hurdle_at(&self, position: f32) -> HurdleDefinition {
+        HurdleDefinition {
+            position,
+            dimensions: self.dimensions,
+            motion: self.motion,
+            water_pit: self.water_pit,
+            overhead_bar: self.overhead_bar,
+        }
+    }
+}
+
+/// A steeplechase water pit right after a hurdle. The hurdle becomes a solid barrier, and landing in
+/// the pit costs `penalty` seconds and replaces the stick's friction and restitution while in it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaterPitDefinition {
+    pub length: f32,
+    pub restitution: f32,
+    pub friction: f32,
+    pub penalty: f32,
+}
+
+impl Default for WaterPitDefinition {
+    fn default() -> Self {
+        Self {
+            length: 3.66,
+            restitution: 0.8,
+            friction: 2.0,
+            penalty: 2.0,
+        }
+    }
+}
+
+/// A banner or low bridge hanging over the player's lane, `offset` after a hurdle. Touching it with
+/// the head injures the player, so the jump over the hurdle before it must not be too high.
+/// `clearance` is the height of the bar's bottom above the ground.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OverheadBarDefinition {
+    pub offset: f32,
+    pub length: f32,
+    pub clearance: f32,
+}
+
+impl Default for OverheadBarDefinition {
+    fn default() -> Self {
+        Self {
+            offset: 4.0,
+            length: 1.5,
+            clearance: 4.0,
+        }
+    }
+}
+
+/// How a hurdle moves during the race. All times are in seconds of race time, so a moving hurdle
+/// is always in the same place at the same point of the race.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ObstacleMotion {
+    /// Slide up from the ground and back down.
+    Slide {
+        amplitude: f32,
+        period: f32,
+        #[serde(default)]
+        phase: f32,
+    },
+    /// Hang from a pivot `pivot_height` above the ground and swing like a pendulum. `amplitude` is
+    /// in radians.
+    Swing {
+        pivot_height: f32,
+        amplitude: f32,
+        period: f32,
+        #[serde(default)]
+        phase: f32,
+    },
+    /// Stay on the ground for `down_time`, rise `height` over `transition_time`, stay there for
+    /// `up_time` and then go back down over `transition_time`.
+    RiseAndFall {
+        height: f32,
+        down_time: f32,
+        up_time: f32,
+        transition_time: f32,
+        #[serde(default)]
+        phase: f32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompetitorDefinition {
+    pub start: f32,
+    pub speed: f32,
+}
+
+/// The race events that can be picked in the race setup screen. The chosen one is kept between
+/// races.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RaceDistance {
+    Hurdles60,
+    Hurdles110,
+    #[default]
+    Hurdles200,
+    Hurdles400,
+}
+
+impl RaceDistance {
+    pub const ALL: [Self; 4] = [
+        Self::Hurdles60,
+        Self::Hurdles110,
+        Self::Hurdles200,
+        Self::Hurdles400,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Hurdles60 => "60m",
+            Self::Hurdles110 => "110m",
+            Self::Hurdles200 => "200m",
+            Self::Hurdles400 => "400m",
+        }
+    }
+
+    pub fn track(&self, track_assets: &TrackAssets) -> Handle<TrackDefinition> {
+        match self {
+            Self::Hurdles60 => track_assets.hurdles_60.clone(),
+            Self::Hurdles110 => track_assets.hurdles_110.clone(),
+            Self::Hurdles200 => track_assets.standard.clone(),
+            Self::Hurdles400 => track_assets.hurdles_400.clone(),
+        }
+    }
+}
+
+/// The track the level is built from. Updated from [`SelectedTrack`] whenever a level is loaded.
+#[derive(Default)]
+pub struct CurrentTrack(pub TrackDefinition);
+
+pub struct SelectedTrack(pub Handle<TrackDefinition>);
+
+/// The ground profile of the current track, as a polyline that covers the entire track bounds.
+///
+/// Two consecutive points with the same `x` form a vertical step.
+#[derive(Default)]
+pub struct Terrain {
+    pub points: Vec<Vec2>,
+}
+
+impl Terrain {
+    pub fn new(track: &TrackDefinition) -> Self {
+        let mut points: Vec<Vec2> = track
+            .terrain
+            .iter()
+            .map(|point| Vec2::new(point.x, point.y))
+            .collect();
+        let left = track.bounds.left;
+        match points.first() {
+            Some(point) if point.x <= left => {}
+            first => {
+                let y = first.map_or(0.0, |point| point.y);
+                points.insert(0, Vec2::new(left, y));
+            }
+        }
+        let right = track.bounds.right;
+        match points.last() {
+            Some(point) if right <= point.x => {}
+            last => {
+                let y = last.map_or(0.0, |point| point.y);
+                points.push(Vec2::new(right, y));
+            }
+        }
+        Self { points }
+    }
+
+    pub fn segments(&self) -> impl '_ + Iterator<Item = (Vec2, Vec2)> {
+        self.points
+            .iter()
+            .zip(self.points.iter().skip(1))
+            .map(|(&start, &end)| (start, end))
+            .filter(|(start, end)| start.x < end.x)
+    }
+
+    fn segment_at(&self, x: f32) -> Option<(Vec2, Vec2)> {
+        self.segments()
+            .find(|(_, end)| x < end.x)
+            .or_else(|| self.segments().last())
+    }
+
+    pub fn height_at(&self, x: f32) -> f32 {
+        if let Some((start, end)) = self.segment_at(x) {
+            let ratio = ((x - start.x) / (end.x - start.x)).clamp(0.0, 1.0);
+            start.y + (end.y - start.y) * ratio
+        } else {
+            0.0
+        }
+    }
+
+    /// The direction of the ground at `x`, as a unit vector pointing right.
+    pub fn tangent_at(&self, x: f32) -> Vec2 {
+        if let Some((start, end)) = self.segment_at(x) {
+            (end - start).normalize()
+        } else {
+            Vec2::X
+        }
+    }
+
+    /// Drop the points behind `x`, keeping the segment `x` is on. For tracks that are streamed and
+    /// never go back.
+    pub fn trim_before(&mut self, x: f32) {
+        let keep_from = self
+            .points
+            .iter()
+            .rposition(|point| point.x <= x)
+            .unwrap_or(0)
+            .min(self.points.len().saturating_sub(2));
+        self.points.drain(..keep_from);
+    }
+
+    pub fn lowest_point(&self) -> f32 {
+        self.points
+            .iter()
+            .map(|point| point.y)
+            .reduce(f32::min)
+            .unwrap_or(0.0)
+    }
+}
+
+#[derive(Default)]
+struct TrackDefinitionLoader;
+
+impl AssetLoader for TrackDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let track: TrackDefinition = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(track));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["track.ron"]
+    }
+}
+
+fn select_default_track(
+    mut commands: Commands,
+    track_assets: Res<TrackAssets>,
+    race_distance: Res<RaceDistance>,
+) {
+    commands.insert_resource(SelectedTrack(race_distance.track(&track_assets)));
+}
+
+fn load_current_track(
+    selected_track: Res<SelectedTrack>,
+    tracks: Res<Assets<TrackDefinition>>,
+    mut current_track: ResMut<CurrentTrack>,
+    mut game_boundaries: ResMut<GameBoundaries>,
+    mut terrain: ResMut<Terrain>,
+) {
+    if let Some(track) = tracks.get(&selected_track.0) {
+        current_track.0 = track.clone();
+    } else {
+        warn!("Selected track is not loaded - reusing the previous track");
+    }
+    *game_boundaries = GameBoundaries {
+        left: current_track.0.bounds.left,
+        right: if current_track.0.endless.is_some() {
+            f32::INFINITY
+        } else {
+            current_track.0.bounds.right
+        },
+    };
+    *terrain = Terrain::new(&current_track.0);
+}
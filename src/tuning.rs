@@ -0,0 +1,111 @@
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::{Deserialize, Serialize};
+
+use crate::loading::TuningAssets;
+
+pub struct TuningPlugin;
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<PogoTuning>();
+        app.init_asset_loader::<PogoTuningLoader>();
+        app.init_resource::<PogoTuning>();
+        app.add_startup_system(watch_for_changes);
+        app.add_system(update_pogo_tuning);
+    }
+}
+
+/// How the pogo feels. The resource starts with the defaults, and is replaced by the tuning asset
+/// whenever it is loaded or the file changes.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid)]
+#[uuid = "8f2d6b1e-5a3c-4e9f-b7d0-2c4a9e6f1b53"]
+pub struct PogoTuning {
+    /// Relative to the center of the player sprite.
+    pub center_of_mass: (f32, f32),
+    pub inertia: f32,
+    pub angular_damping: f32,
+    /// Restitution above 1 is what makes the pogo bounce higher than it fell from.
+    pub stick_restitution: f32,
+    /// Applied per second when rotating with full input.
+    pub control_torque: f32,
+    /// Applied per second when tilted by at least `auto_balance_threshold` radians.
+    pub auto_balance_torque: f32,
+    pub auto_balance_threshold: f32,
+    /// How long it takes to fully charge the pump.
+    pub pump_charge_time: f32,
+    /// Added to the bounce when landing with a full charge.
+    pub pump_impulse: f32,
+    /// How soon after letting go of the pump the stick must land for the charge to be released.
+    pub pump_window: f32,
+    /// How much of the bounce a mistimed pump with a full charge takes away.
+    pub mistimed_pump_damping: f32,
+}
+
+impl Default for PogoTuning {
+    fn default() -> Self {
+        Self {
+            center_of_mass: (0.0, 1.0),
+            inertia: 1.0,
+            angular_damping: 1.0,
+            stick_restitution: 2.0,
+            control_torque: 30.0,
+            auto_balance_torque: 20.0,
+            auto_balance_threshold: 0.2,
+            pump_charge_time: 0.6,
+            pump_impulse: 4.0,
+            pump_window: 0.2,
+            mistimed_pump_damping: 0.5,
+        }
+    }
+}
+
+#[derive(Default)]
+struct PogoTuningLoader;
+
+impl AssetLoader for PogoTuningLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let tuning: PogoTuning = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(tuning));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tuning.ron"]
+    }
+}
+
+/// Only does anything on platforms (and builds) that can watch the asset files.
+fn watch_for_changes(asset_server: Res<AssetServer>) {
+    if let Err(err) = asset_server.watch_for_changes() {
+        warn!("Cannot watch assets for changes: {:?}", err);
+    }
+}
+
+fn update_pogo_tuning(
+    mut reader: EventReader<AssetEvent<PogoTuning>>,
+    tuning_assets: Res<TuningAssets>,
+    tunings: Res<Assets<PogoTuning>>,
+    mut pogo_tuning: ResMut<PogoTuning>,
+) {
+    for event in reader.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle }
+                if *handle == tuning_assets.pogo =>
+            {
+                if let Some(tuning) = tunings.get(handle) {
+                    info!("Pogo tuning loaded: {:?}", tuning);
+                    *pogo_tuning = tuning.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -32,6 +32,15 @@ fn create_view() -> InputView<InputBinding> {
     binding.receiver(BindingInputReceiver::GamepadAxis(GamepadAxisType::DPadX));
     view.add_binding(&binding);
 
+    view.add_binding({
+        ActionBinding::from(InputBinding::Pump)
+            .receiver(BindingInputReceiver::KeyboardKey(KeyCode::Space))
+            .receiver(BindingInputReceiver::KeyboardKey(KeyCode::Down))
+            .receiver(BindingInputReceiver::KeyboardKey(KeyCode::S))
+            .receiver(BindingInputReceiver::GamepadButton(GamepadButtonType::South))
+            .receiver(BindingInputReceiver::GamepadButton(GamepadButtonType::RightTrigger))
+    });
+
     view.add_binding({
         ActionBinding::from(InputBinding::Pause)
             .receiver(BindingInputReceiver::KeyboardKey(KeyCode::Escape))
@@ -55,12 +64,12 @@ fn handle_gamepad_events(
 ) {
     for GamepadEvent(gamepad, event_type) in reader.iter() {
         match event_type {
-            GamepadEventType::Connected => {
-                if !gamepad_services.iter().any(|(_, service)| service.0 == *gamepad) {
-                    commands.spawn()
-                        .insert(create_view())
-                        .insert(EZInputGamepadService(*gamepad));
-                }
+            GamepadEventType::Connected
+                if !gamepad_services.iter().any(|(_, service)| service.0 == *gamepad) =>
+            {
+                commands.spawn()
+                    .insert(create_view())
+                    .insert(EZInputGamepadService(*gamepad));
             }
             GamepadEventType::Disconnected => {
                 for (entity, service) in gamepad_services.iter() {
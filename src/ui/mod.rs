@@ -1,5 +1,6 @@
 mod score;
 mod input;
+mod tricks;
 
 use bevy::prelude::*;
 use bevy_egui_kbgp::bevy_egui::EguiContext;
@@ -7,12 +8,19 @@ use bevy_egui_kbgp::egui;
 use bevy_egui_kbgp::prelude::*;
 use ezinput::prelude::*;
 
+use crate::editor::TrackEditor;
+use crate::game_systems::checkpoints::CheckpointMode;
+use crate::game_systems::endless::BestDistance;
+use crate::game_systems::weather::{Weather, WeatherChoice};
 use crate::global_types::InputBinding;
 use crate::global_types::MenuState;
-use crate::global_types::{AppState, GameOver, PlayerStatus};
+use crate::global_types::{AppState, AssistLevel, GameOver, PlayerStatus, Rider, TrackSeed};
 // use crate::loading::FontAssets;
 use crate::ui::score::ScorePlugin;
 use crate::ui::input::InputPlugin;
+use crate::ui::tricks::TrickPopupsPlugin;
+use crate::loading::TrackAssets;
+use crate::track::{CurrentTrack, RaceDistance, SelectedTrack, TrackDefinition};
 
 pub struct UiPlugin;
 
@@ -22,10 +30,24 @@ impl Plugin for UiPlugin {
 
         app.add_plugin(ScorePlugin);
         app.add_plugin(InputPlugin);
+        app.add_plugin(TrickPopupsPlugin);
+
+        app.init_resource::<AfterRiderSelect>();
 
         app.add_system_set(
             SystemSet::on_update(AppState::Menu(MenuState::Main)).with_system(main_menu),
         );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Menu(MenuState::RiderSelect))
+                .with_system(rider_select_menu),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Menu(MenuState::RaceSetup))
+                .with_system(race_setup_menu),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Menu(MenuState::Settings)).with_system(settings_menu),
+        );
         app.add_system_set(
             SystemSet::on_update(AppState::Menu(MenuState::Pause)).with_system(pause_menu),
         );
@@ -59,6 +81,8 @@ fn pause_unpause_game(
             AppState::Menu(_) => {}
             AppState::ClearLevelAndThenLoad => {}
             AppState::LoadLevel => {}
+            AppState::Crash => {}
+            AppState::Editor => {}
             AppState::Game => {
                 state.set(AppState::Menu(MenuState::Pause)).unwrap();
             }
@@ -77,19 +101,66 @@ fn menu_layout(egui_context: &egui::Context, dlg: impl FnOnce(&mut egui::Ui)) {
         });
 }
 
+/// Where the rider select screen leads - it is shown both before the race setup and before
+/// starting an endless run.
+struct AfterRiderSelect(AppState);
+
+impl Default for AfterRiderSelect {
+    fn default() -> Self {
+        Self(AppState::Menu(MenuState::RaceSetup))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn main_menu(
     mut egui_context: ResMut<EguiContext>,
     mut state: ResMut<State<AppState>>,
     #[cfg(not(target_arch = "wasm32"))] mut exit: EventWriter<bevy::app::AppExit>,
+    mut after_rider_select: ResMut<AfterRiderSelect>,
+    mut track_seed: ResMut<TrackSeed>,
+    mut selected_track: ResMut<SelectedTrack>,
+    track_assets: Res<TrackAssets>,
+    tracks: Res<Assets<TrackDefinition>>,
 ) {
     menu_layout(egui_context.ctx_mut(), |ui| {
         if ui
-            .button("Start")
+            .button("Race")
             .kbgp_navigation()
             .kbgp_initial_focus()
             .clicked()
         {
-            state.set(AppState::ClearLevelAndThenLoad).unwrap();
+            after_rider_select.0 = AppState::Menu(MenuState::RaceSetup);
+            state.set(AppState::Menu(MenuState::RiderSelect)).unwrap();
+            ui.kbgp_clear_input();
+        }
+        if ui
+            .add_enabled(
+                tracks.get(&track_assets.endless).is_some(),
+                egui::Button::new("Endless"),
+            )
+            .kbgp_navigation()
+            .clicked()
+        {
+            *track_seed = TrackSeed::random();
+            selected_track.0 = track_assets.endless.clone();
+            after_rider_select.0 = AppState::ClearLevelAndThenLoad;
+            state.set(AppState::Menu(MenuState::RiderSelect)).unwrap();
+            ui.kbgp_clear_input();
+        }
+        if ui
+            .add_enabled(
+                tracks.get(&track_assets.standard).is_some(),
+                egui::Button::new("Track Editor"),
+            )
+            .kbgp_navigation()
+            .clicked()
+        {
+            state.set(AppState::Editor).unwrap();
+            ui.kbgp_clear_input();
+        }
+        if ui.button("Settings").kbgp_navigation().clicked() {
+            state.set(AppState::Menu(MenuState::Settings)).unwrap();
+            ui.kbgp_clear_input();
         }
         #[cfg(not(target_arch = "wasm32"))]
         if ui.button("Exit").kbgp_navigation().clicked() {
@@ -98,10 +169,162 @@ fn main_menu(
     });
 }
 
+fn rider_select_menu(
+    mut egui_context: ResMut<EguiContext>,
+    mut state: ResMut<State<AppState>>,
+    mut rider: ResMut<Rider>,
+    after_rider_select: Res<AfterRiderSelect>,
+) {
+    menu_layout(egui_context.ctx_mut(), |ui| {
+        ui.label("Choose your rider:");
+        ui.horizontal(|ui| {
+            for option in Rider::ALL {
+                let button = ui
+                    .selectable_label(*rider == option, option.label())
+                    .kbgp_navigation();
+                let button = if *rider == option {
+                    button.kbgp_initial_focus()
+                } else {
+                    button
+                };
+                if button.clicked() {
+                    *rider = option;
+                }
+            }
+        });
+        ui.label(rider.description());
+        if ui.button("Continue").kbgp_navigation().clicked() {
+            state.set(after_rider_select.0.clone()).unwrap();
+            ui.kbgp_clear_input();
+        }
+        if ui.button("Back").kbgp_navigation().clicked() {
+            state.set(AppState::Menu(MenuState::Main)).unwrap();
+            ui.kbgp_clear_input();
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn race_setup_menu(
+    mut egui_context: ResMut<EguiContext>,
+    mut state: ResMut<State<AppState>>,
+    mut race_distance: ResMut<RaceDistance>,
+    mut track_seed: ResMut<TrackSeed>,
+    mut seed_text: Local<String>,
+    mut checkpoint_mode: ResMut<CheckpointMode>,
+    mut weather_choice: ResMut<WeatherChoice>,
+    mut selected_track: ResMut<SelectedTrack>,
+    track_assets: Res<TrackAssets>,
+    tracks: Res<Assets<TrackDefinition>>,
+) {
+    menu_layout(egui_context.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            for distance in RaceDistance::ALL {
+                let button = ui
+                    .selectable_label(*race_distance == distance, distance.label())
+                    .kbgp_navigation();
+                let button = if *race_distance == distance {
+                    button.kbgp_initial_focus()
+                } else {
+                    button
+                };
+                if button.clicked() {
+                    *race_distance = distance;
+                }
+            }
+        });
+        let track = tracks.get(race_distance.track(&track_assets));
+        if let Some(track) = track {
+            ui.label(&track.name);
+        }
+        let track_loaded = track.is_some();
+        ui.checkbox(&mut checkpoint_mode.0, "Checkpoints")
+            .on_hover_text("Respawn at the last checkpoint instead of ending the race")
+            .kbgp_navigation();
+        ui.horizontal(|ui| {
+            ui.label("Weather:");
+            let choices = std::iter::once(WeatherChoice::Random)
+                .chain(Weather::ALL.into_iter().map(WeatherChoice::Fixed));
+            for choice in choices {
+                if ui
+                    .selectable_label(*weather_choice == choice, choice.label())
+                    .kbgp_navigation()
+                    .clicked()
+                {
+                    *weather_choice = choice;
+                }
+            }
+        });
+        if ui
+            .add_enabled(track_loaded, egui::Button::new("Start"))
+            .kbgp_navigation()
+            .clicked()
+        {
+            *track_seed = TrackSeed::random();
+            selected_track.0 = race_distance.track(&track_assets);
+            state.set(AppState::ClearLevelAndThenLoad).unwrap();
+        }
+        ui.horizontal(|ui| {
+            ui.label("Seed:");
+            ui.add(egui::TextEdit::singleline(&mut *seed_text).desired_width(150.0))
+                .kbgp_navigation();
+        });
+        let typed_seed = seed_text.trim().parse().ok().map(TrackSeed);
+        if ui
+            .add_enabled(
+                track_loaded && typed_seed.is_some(),
+                egui::Button::new("Start on Seed"),
+            )
+            .kbgp_navigation()
+            .clicked()
+        {
+            if let Some(typed_seed) = typed_seed {
+                *track_seed = typed_seed;
+                selected_track.0 = race_distance.track(&track_assets);
+                state.set(AppState::ClearLevelAndThenLoad).unwrap();
+            }
+        }
+        if ui.button("Back").kbgp_navigation().clicked() {
+            state.set(AppState::Menu(MenuState::RiderSelect)).unwrap();
+            ui.kbgp_clear_input();
+        }
+    });
+}
+
+fn settings_menu(
+    mut egui_context: ResMut<EguiContext>,
+    mut state: ResMut<State<AppState>>,
+    mut assist_level: ResMut<AssistLevel>,
+) {
+    menu_layout(egui_context.ctx_mut(), |ui| {
+        ui.label("Balance assist:");
+        ui.horizontal(|ui| {
+            for level in AssistLevel::ALL {
+                let button = ui
+                    .selectable_label(*assist_level == level, level.label())
+                    .kbgp_navigation();
+                let button = if *assist_level == level {
+                    button.kbgp_initial_focus()
+                } else {
+                    button
+                };
+                if button.clicked() {
+                    *assist_level = level;
+                }
+            }
+        });
+        if ui.button("Back").kbgp_navigation().clicked() {
+            state.set(AppState::Menu(MenuState::Main)).unwrap();
+            ui.kbgp_clear_input();
+        }
+    });
+}
+
 fn pause_menu(
     mut egui_context: ResMut<EguiContext>,
     mut state: ResMut<State<AppState>>,
     #[cfg(not(target_arch = "wasm32"))] mut exit: EventWriter<bevy::app::AppExit>,
+    track_editor: Res<TrackEditor>,
 ) {
     menu_layout(egui_context.ctx_mut(), |ui| {
         if ui
@@ -112,6 +335,10 @@ fn pause_menu(
         {
             state.set(AppState::Game).unwrap();
         }
+        if track_editor.test_run && ui.button("Back to Editor").kbgp_navigation().clicked() {
+            state.set(AppState::Editor).unwrap();
+            ui.kbgp_clear_input();
+        }
         if ui.button("Main Menu").kbgp_navigation().clicked() {
             state.set(AppState::Menu(MenuState::Main)).unwrap();
             ui.kbgp_clear_input();
@@ -123,23 +350,44 @@ fn pause_menu(
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 fn game_over_menu(
     mut egui_context: ResMut<EguiContext>,
     mut state: ResMut<State<AppState>>,
     #[cfg(not(target_arch = "wasm32"))] mut exit: EventWriter<bevy::app::AppExit>,
     game_over_state: Res<State<Option<GameOver>>>,
     player_status: Res<PlayerStatus>,
+    track_seed: Res<TrackSeed>,
+    current_track: Res<CurrentTrack>,
+    best_distance: Res<BestDistance>,
+    track_editor: Res<TrackEditor>,
+    weather: Res<Weather>,
 ) {
     menu_layout(egui_context.ctx_mut(), |ui| {
-        if ui
-            .button("Main Menu")
-            .kbgp_navigation()
-            .kbgp_initial_focus()
-            .clicked()
+        if track_editor.test_run
+            && ui
+                .button("Back to Editor")
+                .kbgp_navigation()
+                .kbgp_initial_focus()
+                .clicked()
         {
+            state.set(AppState::Editor).unwrap();
+            ui.kbgp_clear_input();
+        }
+        let main_menu_button = ui.button("Main Menu").kbgp_navigation();
+        let main_menu_button = if track_editor.test_run {
+            main_menu_button
+        } else {
+            main_menu_button.kbgp_initial_focus()
+        };
+        if main_menu_button.clicked() {
             state.set(AppState::Menu(MenuState::Main)).unwrap();
             ui.kbgp_clear_input();
         }
+        if ui.button("Retry Track").kbgp_navigation().clicked() {
+            state.set(AppState::ClearLevelAndThenLoad).unwrap();
+            ui.kbgp_clear_input();
+        }
         #[cfg(not(target_arch = "wasm32"))]
         if ui.button("Exit").kbgp_navigation().clicked() {
             exit.send(bevy::app::AppExit);
@@ -174,6 +422,45 @@ fn game_over_menu(
                     ));
                 }
             }
+            ui.label(format!("Event: {}", current_track.0.name));
+            ui.label(format!("Weather: {}", weather.label()));
+            ui.label(format!("Rider: {}", player_status.rider.label()));
+            ui.label(format!("Assist: {}", player_status.assist_level.label()));
+            if 0 < player_status.respawns {
+                ui.label(format!("Respawned {} times", player_status.respawns));
+            }
+            if 0 < player_status.hurdles_knocked {
+                ui.label(format!("Knocked {} hurdles", player_status.hurdles_knocked));
+            }
+            if !player_status.penalty.is_zero() {
+                ui.label(format!(
+                    "Time penalties: +{:.1}s",
+                    player_status.penalty.as_secs_f32()
+                ));
+            }
+            if !current_track.0.pickups.is_empty() {
+                ui.label(format!("Score: {}", player_status.score));
+            }
+            if 0 < player_status.trick_score {
+                ui.label(format!("Trick score: {}", player_status.trick_score));
+            }
+            if !player_status.time_bonus.is_zero() {
+                ui.label(format!(
+                    "Time bonuses: -{:.1}s",
+                    player_status.time_bonus.as_secs_f32()
+                ));
+            }
+            if current_track.0.endless.is_some() {
+                if best_distance.new_record {
+                    ui.colored_label(egui::Color32::GREEN, "New best distance!");
+                } else {
+                    ui.label(format!(
+                        "Best distance: {:.1}m",
+                        best_distance.distance(player_status.assist_level)
+                    ));
+                }
+            }
+            ui.label(format!("Track seed: {}", track_seed.0));
         }
     });
 }
@@ -1,7 +1,14 @@
 use bevy::prelude::*;
 
-use crate::global_types::PlayerStatus;
+use crate::game_systems::checkpoints::CheckpointMode;
+use crate::game_systems::endless::BestDistance;
+use crate::game_systems::power_ups::{power_up, ActivePowerUps};
+use crate::game_systems::pump::Pump;
+use crate::game_systems::tricks::Tricks;
+use crate::game_systems::wind::Wind;
+use crate::global_types::{PlayerStatus, TrackSeed};
 use crate::loading::FontAssets;
+use crate::track::{CurrentTrack, HurdleRules};
 
 pub struct ScorePlugin;
 
@@ -18,7 +25,7 @@ struct ScoreDisplayText;
 fn setup_score_display(mut commands: Commands, font_assets: Res<FontAssets>) {
     let mut cmd = commands.spawn_bundle(NodeBundle {
         style: Style {
-            size: Size::new(Val::Px(0.0), Val::Px(90.0)),
+            size: Size::new(Val::Px(0.0), Val::Auto),
             position_type: PositionType::Absolute,
             justify_content: JustifyContent::FlexStart,
             position: Rect {
@@ -34,8 +41,7 @@ fn setup_score_display(mut commands: Commands, font_assets: Res<FontAssets>) {
     let text_style = TextStyle {
         font: font_assets.fira_sans.clone(),
         font_size: 30.0,
-        color: Color::WHITE.into(),
-        ..Default::default()
+        color: Color::WHITE,
     };
     cmd.with_children(|commands| {
         let mut cmd = commands.spawn_bundle(TextBundle {
@@ -81,6 +87,90 @@ fn setup_score_display(mut commands: Commands, font_assets: Res<FontAssets>) {
                         value: String::new(),
                         style: text_style.clone(),
                     },
+                    TextSection {
+                        value: "\n".to_owned(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: "Seed: ".to_owned(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    // Label for the knocked hurdles line - only shown when knocking hurdles is allowed
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    // Label for the respawns line - only shown when playing with checkpoints
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    // Label for the best distance line - only shown on endless tracks
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    // Label for the wind indicator - only shown on tracks with wind
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    // Label for the score line - only shown on tracks with pickups
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    // Label for the active power-ups line - only shown while there are any
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    // Label for the pump charge - only shown while the pump is charged
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    // Label for the trick score - only shown after landing tricks
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: text_style.clone(),
+                    },
                 ],
                 alignment: TextAlignment {
                     vertical: VerticalAlign::Bottom,
@@ -93,14 +183,113 @@ fn setup_score_display(mut commands: Commands, font_assets: Res<FontAssets>) {
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_score_display(
     mut query: Query<&mut Text, With<ScoreDisplayText>>,
     player_status: Res<PlayerStatus>,
+    track_seed: Res<TrackSeed>,
+    current_track: Res<CurrentTrack>,
+    checkpoint_mode: Res<CheckpointMode>,
+    best_distance: Res<BestDistance>,
+    wind: Res<Wind>,
+    active_power_ups: Res<ActivePowerUps>,
+    pump: Res<Pump>,
+    tricks: Res<Tricks>,
 ) {
     for mut score_text in query.iter_mut() {
         score_text.sections[1].value = format!("{:.1}m", player_status.distance_traveled);
         score_text.sections[4].value = player_status.format_time();
         score_text.sections[7].value = player_status.place().to_string();
         score_text.sections[9].value = player_status.total_runners().to_string();
+        score_text.sections[12].value = track_seed.0.to_string();
+        if let HurdleRules::KnockDown { .. } = current_track.0.hurdle_rules {
+            score_text.sections[13].value = "\nKnocked: ".to_owned();
+            score_text.sections[14].value = player_status.hurdles_knocked.to_string();
+        } else {
+            score_text.sections[13].value = String::new();
+            score_text.sections[14].value = String::new();
+        }
+        if checkpoint_mode.0 {
+            score_text.sections[15].value = "\nRespawns: ".to_owned();
+            score_text.sections[16].value = player_status.respawns.to_string();
+        } else {
+            score_text.sections[15].value = String::new();
+            score_text.sections[16].value = String::new();
+        }
+        if current_track.0.endless.is_some() {
+            score_text.sections[17].value = "\nBest: ".to_owned();
+            score_text.sections[18].value =
+                format!("{:.1}m", best_distance.distance(player_status.assist_level));
+        } else {
+            score_text.sections[17].value = String::new();
+            score_text.sections[18].value = String::new();
+        }
+        if wind.blows() {
+            let force = wind.force_at(player_status.distance_traveled, player_status.time);
+            score_text.sections[19].value = "\nWind: ".to_owned();
+            score_text.sections[20].value = format_wind(force);
+        } else {
+            score_text.sections[19].value = String::new();
+            score_text.sections[20].value = String::new();
+        }
+        if !current_track.0.pickups.is_empty() {
+            score_text.sections[21].value = "\nScore: ".to_owned();
+            score_text.sections[22].value = player_status.score.to_string();
+        } else {
+            score_text.sections[21].value = String::new();
+            score_text.sections[22].value = String::new();
+        }
+        let power_ups = active_power_ups
+            .iter()
+            .map(|active| {
+                let remaining = active.expires_at.saturating_sub(player_status.time);
+                format!(
+                    "{} {:.1}s",
+                    power_up(active.kind).name(),
+                    remaining.as_secs_f32()
+                )
+            })
+            .collect::<Vec<_>>();
+        if !power_ups.is_empty() {
+            score_text.sections[23].value = "\nPower-ups: ".to_owned();
+            score_text.sections[24].value = power_ups.join(", ");
+        } else {
+            score_text.sections[23].value = String::new();
+            score_text.sections[24].value = String::new();
+        }
+        if 0.0 < pump.charge {
+            score_text.sections[25].value = "\nPump: ".to_owned();
+            score_text.sections[26].value = format!("{:.0}%", pump.charge * 100.0);
+        } else {
+            score_text.sections[25].value = String::new();
+            score_text.sections[26].value = String::new();
+        }
+        if 0 < player_status.trick_score {
+            score_text.sections[27].value = "\nTricks: ".to_owned();
+            score_text.sections[28].value = if 1 < tricks.multiplier() {
+                format!(
+                    "{} (combo x{})",
+                    player_status.trick_score,
+                    tricks.multiplier()
+                )
+            } else {
+                player_status.trick_score.to_string()
+            };
+        } else {
+            score_text.sections[27].value = String::new();
+            score_text.sections[28].value = String::new();
+        }
+    }
+}
+
+/// An arrow pointing where the wind blows, rounded to the closest of eight directions, and its
+/// strength.
+fn format_wind(force: Vec2) -> String {
+    const ARROWS: [char; 8] = ['→', '↗', '↑', '↖', '←', '↙', '↓', '↘'];
+    let strength = force.length();
+    if strength < 0.05 {
+        return "calm".to_owned();
     }
+    let octant = (force.y.atan2(force.x) / std::f32::consts::FRAC_PI_4).round() as i32;
+    format!("{} {:.1}", ARROWS[octant.rem_euclid(8) as usize], strength)
 }
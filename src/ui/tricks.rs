@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::game_systems::tricks::{Trick, TrickLanded};
+use crate::global_types::DespawnWithLevel;
+use crate::loading::FontAssets;
+
+pub struct TrickPopupsPlugin;
+
+impl Plugin for TrickPopupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(show_trick_popups);
+        app.add_system(update_trick_popups);
+    }
+}
+
+/// How long a popup stays on screen, in seconds.
+const POPUP_DURATION: f32 = 1.5;
+
+/// How far up the screen a popup floats before it disappears, in percents of the screen height.
+const POPUP_RISE: f32 = 10.0;
+
+#[derive(Component)]
+struct TrickPopup {
+    timer: Timer,
+}
+
+fn format_trick_landed(trick_landed: &TrickLanded) -> String {
+    let names = trick_landed
+        .tricks
+        .iter()
+        .map(Trick::name)
+        .collect::<Vec<_>>()
+        .join(" + ");
+    if 1 < trick_landed.multiplier {
+        format!(
+            "{}\n+{} (x{})",
+            names, trick_landed.points, trick_landed.multiplier
+        )
+    } else {
+        format!("{}\n+{}", names, trick_landed.points)
+    }
+}
+
+fn show_trick_popups(
+    mut commands: Commands,
+    mut reader: EventReader<TrickLanded>,
+    font_assets: Res<FontAssets>,
+    popups_query: Query<Entity, With<TrickPopup>>,
+) {
+    let trick_landed = if let Some(trick_landed) = reader.iter().last() {
+        trick_landed
+    } else {
+        return;
+    };
+    // Only show the latest tricks, so that popups don't pile on each other
+    for entity in popups_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    // A full-width node, so that the text can be centered
+    let mut cmd = commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Auto),
+            position_type: PositionType::Absolute,
+            justify_content: JustifyContent::Center,
+            position: Rect {
+                top: Val::Percent(25.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        color: Color::NONE.into(),
+        ..Default::default()
+    });
+    cmd.insert(TrickPopup {
+        timer: Timer::from_seconds(POPUP_DURATION, false),
+    });
+    cmd.insert(DespawnWithLevel);
+    cmd.with_children(|commands| {
+        commands.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                format_trick_landed(trick_landed),
+                TextStyle {
+                    font: font_assets.fira_sans.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb(1.0, 0.85, 0.1),
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Center,
+                    horizontal: HorizontalAlign::Center,
+                },
+            ),
+            ..Default::default()
+        });
+    });
+}
+
+fn update_trick_popups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut popups_query: Query<(Entity, &mut TrickPopup, &mut Style, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (entity, mut popup, mut style, children) in popups_query.iter_mut() {
+        popup.timer.tick(time.delta());
+        if popup.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let progress = popup.timer.percent();
+        style.position.top = Val::Percent(25.0 - POPUP_RISE * progress);
+        for &child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(child) {
+                for section in text.sections.iter_mut() {
+                    section.style.color.set_a(1.0 - progress * progress);
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use rand::Rng;
+
 pub trait ReorderItem {
     type Type;
 
@@ -37,3 +39,12 @@ macro_rules! entities_ordered_by_type {
     }}
 }
 pub(crate) use entities_ordered_by_type;
+
+/// A random value in `min..max`, or `min` if the range is empty.
+pub fn random_between(rng: &mut impl Rng, min: f32, max: f32) -> f32 {
+    if min < max {
+        rng.gen_range(min..max)
+    } else {
+        min
+    }
+}